@@ -0,0 +1,11 @@
+#!/usr/bin/env bash
+
+DIR=$(realpath $0) && DIR=${DIR%/*}
+cd $DIR
+set -ex
+
+cbindgen --config cbindgen.toml --output include/add_space.h
+cargo build --release -p add_space_ffi --features ffi
+LIB=$(realpath ../target/release)
+cc -Wall -Werror -Iinclude tests/ffi.c -L$LIB -ladd_space_ffi -o $LIB/ffi_test
+LD_LIBRARY_PATH=$LIB DYLD_LIBRARY_PATH=$LIB $LIB/ffi_test
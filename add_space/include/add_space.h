@@ -0,0 +1,85 @@
+/* Generated by cbindgen from src/ffi.rs, run ./ffi.sh after changing it. */
+
+#ifndef ADD_SPACE_H
+#define ADD_SPACE_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Parsed options, reused across calls.
+ */
+typedef struct AddSpaceOptions AddSpaceOptions;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Formats `txt` with the default options, NULL when `txt` is NULL or not UTF-8.
+ *
+ * # Safety
+ *
+ * `txt` is NULL or a NUL-terminated string.
+ */
+char *add_space(const char *txt);
+
+/**
+ * Like [`add_space`], also removing spurious spaces from CJK text.
+ *
+ * # Safety
+ *
+ * `txt` is NULL or a NUL-terminated string.
+ */
+char *add_space_normalize(const char *txt);
+
+/**
+ * Options from TOML with the keys of `.add_space.toml`, the defaults when `toml` is NULL. On
+ * error returns NULL and, when `err` is not NULL, stores the message there, to be released with
+ * [`add_space_free`].
+ *
+ * # Safety
+ *
+ * `toml` is NULL or a NUL-terminated string, `err` is NULL or writable.
+ */
+struct AddSpaceOptions *add_space_options_new(const char *toml, char **err);
+
+/**
+ * Releases options from [`add_space_options_new`], NULL is ignored.
+ *
+ * # Safety
+ *
+ * `opts` is NULL or from [`add_space_options_new`], and not used afterwards.
+ */
+void add_space_options_free(struct AddSpaceOptions *opts);
+
+/**
+ * Formats `txt` read as `mode`: `text` (when NULL), `markdown`, `html`, a language, `rust`,
+ * `ts`, `py`, `go`, a subtitle format, `srt`, `vtt`, `ass`, a data format, `json`, `yaml`,
+ * `toml`, or a translation format, `po`, `xliff`. `opts` may be NULL for the defaults. NULL when
+ * `txt` is NULL or not UTF-8, or the mode is unknown.
+ *
+ * # Safety
+ *
+ * `opts` is NULL or from [`add_space_options_new`], `txt` and `mode` are NULL or NUL-terminated
+ * strings.
+ */
+char *add_space_format(const struct AddSpaceOptions *opts, const char *txt, const char *mode);
+
+/**
+ * Releases a string returned by this library, NULL is ignored.
+ *
+ * # Safety
+ *
+ * `s` is NULL or returned by this library, and not used afterwards.
+ */
+void add_space_free(char *s);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* ADD_SPACE_H */
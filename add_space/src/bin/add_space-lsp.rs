@@ -0,0 +1,353 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use add_space::{Conf, Edit, Mode, Options};
+use aok::{OK, Void};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+  CodeAction, CodeActionKind, CodeActionOptions, CodeActionOrCommand, CodeActionParams,
+  CodeActionProviderCapability, Diagnostic, DiagnosticSeverity, DidChangeTextDocumentParams,
+  DidCloseTextDocumentParams, DidOpenTextDocumentParams, DocumentFormattingParams,
+  DocumentRangeFormattingParams, MessageType, NumberOrString, OneOf, Position,
+  PublishDiagnosticsParams, Range, ServerCapabilities, ShowMessageParams,
+  TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit, Uri, WorkspaceEdit,
+  notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+    PublishDiagnostics, ShowMessage,
+  },
+  request::{CodeActionRequest, Formatting, RangeFormatting, Request as _},
+};
+
+const SOURCE: &str = "add_space";
+
+/// Byte offsets <-> LSP positions (UTF-16 columns).
+struct LineIndex<'a> {
+  txt: &'a str,
+  starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+  fn new(txt: &'a str) -> Self {
+    let starts = std::iter::once(0)
+      .chain(txt.match_indices('\n').map(|(i, _)| i + 1))
+      .collect();
+    Self { txt, starts }
+  }
+
+  fn position(&self, offset: usize) -> Position {
+    let line = self.starts.partition_point(|&s| s <= offset) - 1;
+    let col = self.txt[self.starts[line]..offset].encode_utf16().count();
+    Position::new(line as u32, col as u32)
+  }
+
+  fn offset(&self, pos: Position) -> usize {
+    let Some(&start) = self.starts.get(pos.line as usize) else {
+      return self.txt.len();
+    };
+    let mut col = 0;
+    for (i, c) in self.txt[start..].char_indices() {
+      if col >= pos.character as usize || c == '\n' {
+        return start + i;
+      }
+      col += c.len_utf16();
+    }
+    self.txt.len()
+  }
+
+  fn range(&self, range: &std::ops::Range<usize>) -> Range {
+    Range::new(self.position(range.start), self.position(range.end))
+  }
+}
+
+struct Doc {
+  txt: String,
+  mode: Mode,
+  options: Options,
+  included: bool,
+  /// Broken configuration, shown to the user, the document is left alone like the CLI does
+  error: Option<String>,
+}
+
+impl Doc {
+  fn new(uri: &Uri, language_id: &str, txt: String) -> Self {
+    let path = path(uri);
+    let (conf, error) = match path.as_ref().map(Conf::find) {
+      Some(Err(err)) => (Conf::default(), Some(err.to_string())),
+      Some(Ok(conf)) => (conf.unwrap_or_default(), None),
+      None => (Conf::default(), None),
+    };
+    let mode = match &path {
+      Some(p) if p.extension().is_some() => conf.mode(p),
+      _ if language_id == "markdown" => Mode::Markdown,
+      _ if language_id == "html" => Mode::Html,
+      _ => Mode::Text,
+    };
+    Self {
+      txt,
+      mode,
+      included: error.is_none() && path.as_ref().is_none_or(|p| conf.is_included(p)),
+      options: conf.options,
+      error,
+    }
+  }
+
+  fn edits(&self) -> Vec<Edit> {
+    if self.included {
+      self.mode.edits(&self.txt, &self.options)
+    } else {
+      Vec::new()
+    }
+  }
+
+  fn text_edit(&self, index: &LineIndex, edit: &Edit) -> TextEdit {
+    TextEdit::new(index.range(&edit.range), edit.text.clone())
+  }
+
+  fn diagnostic(&self, index: &LineIndex, edit: &Edit) -> Diagnostic {
+    // an insertion point is hard to see, mark the chars around it
+    let range = if edit.range.is_empty() {
+      let start = self.txt[..edit.range.start]
+        .char_indices()
+        .next_back()
+        .map_or(edit.range.start, |(i, _)| i);
+      let end = self.txt[edit.range.end..]
+        .chars()
+        .next()
+        .map_or(edit.range.end, |c| edit.range.end + c.len_utf8());
+      start..end
+    } else {
+      edit.range.clone()
+    };
+    Diagnostic {
+      range: index.range(&range),
+      severity: Some(DiagnosticSeverity::WARNING),
+      code: Some(NumberOrString::String(format!("{:?}", edit.rule))),
+      source: Some(SOURCE.into()),
+      message: edit.rule.to_string(),
+      ..Default::default()
+    }
+  }
+}
+
+fn path(uri: &Uri) -> Option<PathBuf> {
+  if uri.scheme().is_none_or(|s| s.as_str() != "file") {
+    return None;
+  }
+  Some(PathBuf::from(
+    uri.path().as_estr().decode().into_string_lossy().as_ref(),
+  ))
+}
+
+struct Server {
+  conn: Connection,
+  docs: HashMap<Uri, Doc>,
+}
+
+impl Server {
+  fn run(&mut self) -> Void {
+    while let Ok(msg) = self.conn.receiver.recv() {
+      match msg {
+        Message::Request(req) => {
+          if self.conn.handle_shutdown(&req)? {
+            return OK;
+          }
+          let resp = self.request(req);
+          self.conn.sender.send(resp.into())?;
+        }
+        Message::Notification(n) => {
+          // bad params are shown, the server keeps serving
+          if let Err(err) = self.notify(n) {
+            self.show_error(&err.to_string())?;
+          }
+        }
+        Message::Response(_) => {}
+      }
+    }
+    OK
+  }
+
+  fn request(&self, req: Request) -> Response {
+    let id = req.id.clone();
+    let result = match req.method.as_str() {
+      Formatting::METHOD => req
+        .extract::<DocumentFormattingParams>(Formatting::METHOD)
+        .map(|(_, p)| serde_json::to_value(self.format(&p.text_document.uri, None))),
+      RangeFormatting::METHOD => req
+        .extract::<DocumentRangeFormattingParams>(RangeFormatting::METHOD)
+        .map(|(_, p)| serde_json::to_value(self.format(&p.text_document.uri, Some(p.range)))),
+      CodeActionRequest::METHOD => req
+        .extract::<CodeActionParams>(CodeActionRequest::METHOD)
+        .map(|(_, p)| serde_json::to_value(self.code_action(&p))),
+      method => {
+        return Response::new_err(
+          id,
+          ErrorCode::MethodNotFound as i32,
+          format!("unknown method {method}"),
+        );
+      }
+    };
+    match result {
+      Ok(Ok(value)) => Response {
+        id,
+        result: Some(value),
+        error: None,
+      },
+      Ok(Err(err)) => Response::new_err(id, ErrorCode::InternalError as i32, err.to_string()),
+      Err(err) => Response::new_err(id, ErrorCode::InvalidParams as i32, format!("{err:?}")),
+    }
+  }
+
+  /// Edits as LSP text edits, those starting inside `range` only when given.
+  fn format(&self, uri: &Uri, range: Option<Range>) -> Option<Vec<TextEdit>> {
+    let doc = self.docs.get(uri)?;
+    let index = LineIndex::new(&doc.txt);
+    let within = range.map(|r| index.offset(r.start)..index.offset(r.end));
+    Some(
+      doc
+        .edits()
+        .iter()
+        .filter(|e| {
+          within
+            .as_ref()
+            .is_none_or(|w| w.contains(&e.range.start) || w.end == e.range.start)
+        })
+        .map(|e| doc.text_edit(&index, e))
+        .collect(),
+    )
+  }
+
+  fn code_action(&self, params: &CodeActionParams) -> Option<Vec<CodeActionOrCommand>> {
+    let uri = &params.text_document.uri;
+    let doc = self.docs.get(uri)?;
+    let index = LineIndex::new(&doc.txt);
+    let edits = doc.edits();
+    let start = index.offset(params.range.start);
+    let end = index.offset(params.range.end);
+    let workspace_edit = |edits: Vec<TextEdit>| WorkspaceEdit {
+      changes: Some(HashMap::from([(uri.clone(), edits)])),
+      ..Default::default()
+    };
+
+    let mut r: Vec<CodeActionOrCommand> = edits
+      .iter()
+      .filter(|e| e.range.start <= end && e.range.end >= start)
+      .map(|e| {
+        CodeActionOrCommand::CodeAction(CodeAction {
+          title: format!("Fix: {}", e.rule),
+          kind: Some(CodeActionKind::QUICKFIX),
+          diagnostics: Some(vec![doc.diagnostic(&index, e)]),
+          edit: Some(workspace_edit(vec![doc.text_edit(&index, e)])),
+          is_preferred: Some(true),
+          ..Default::default()
+        })
+      })
+      .collect();
+
+    if !edits.is_empty() {
+      r.push(CodeActionOrCommand::CodeAction(CodeAction {
+        title: "Fix all add_space issues".into(),
+        kind: Some(CodeActionKind::SOURCE_FIX_ALL),
+        edit: Some(workspace_edit(
+          edits.iter().map(|e| doc.text_edit(&index, e)).collect(),
+        )),
+        ..Default::default()
+      }));
+    }
+    Some(r)
+  }
+
+  fn notify(&mut self, n: Notification) -> Void {
+    match n.method.as_str() {
+      DidOpenTextDocument::METHOD => {
+        let p: DidOpenTextDocumentParams = serde_json::from_value(n.params)?;
+        let doc = &p.text_document;
+        let new = Doc::new(&doc.uri, &doc.language_id, doc.text.clone());
+        if let Some(err) = &new.error {
+          self.show_error(err)?;
+        }
+        self.docs.insert(doc.uri.clone(), new);
+        self.publish(&doc.uri, Some(doc.version))?;
+      }
+      DidChangeTextDocument::METHOD => {
+        let p: DidChangeTextDocumentParams = serde_json::from_value(n.params)?;
+        if let Some(doc) = self.docs.get_mut(&p.text_document.uri)
+          && let Some(change) = p.content_changes.into_iter().last()
+        {
+          doc.txt = change.text;
+        }
+        self.publish(&p.text_document.uri, Some(p.text_document.version))?;
+      }
+      DidCloseTextDocument::METHOD => {
+        let p: DidCloseTextDocumentParams = serde_json::from_value(n.params)?;
+        self.docs.remove(&p.text_document.uri);
+        self.publish(&p.text_document.uri, None)?;
+      }
+      _ => {}
+    }
+    OK
+  }
+
+  fn show_error(&self, err: &str) -> Void {
+    self.conn.sender.send(
+      Notification::new(
+        ShowMessage::METHOD.into(),
+        ShowMessageParams {
+          typ: MessageType::ERROR,
+          message: format!("{SOURCE}: {err}"),
+        },
+      )
+      .into(),
+    )?;
+    OK
+  }
+
+  fn publish(&self, uri: &Uri, version: Option<i32>) -> Void {
+    let diagnostics = match self.docs.get(uri) {
+      Some(doc) => {
+        let index = LineIndex::new(&doc.txt);
+        doc
+          .edits()
+          .iter()
+          .map(|e| doc.diagnostic(&index, e))
+          .collect()
+      }
+      None => Vec::new(),
+    };
+    self.conn.sender.send(
+      Notification::new(
+        PublishDiagnostics::METHOD.into(),
+        PublishDiagnosticsParams {
+          uri: uri.clone(),
+          diagnostics,
+          version,
+        },
+      )
+      .into(),
+    )?;
+    OK
+  }
+}
+
+fn main() -> Void {
+  let (conn, io) = Connection::stdio();
+  let capabilities = serde_json::to_value(ServerCapabilities {
+    text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+    document_formatting_provider: Some(OneOf::Left(true)),
+    document_range_formatting_provider: Some(OneOf::Left(true)),
+    code_action_provider: Some(CodeActionProviderCapability::Options(CodeActionOptions {
+      code_action_kinds: Some(vec![
+        CodeActionKind::QUICKFIX,
+        CodeActionKind::SOURCE_FIX_ALL,
+      ]),
+      ..Default::default()
+    })),
+    ..Default::default()
+  })?;
+  conn.initialize(capabilities)?;
+  Server {
+    conn,
+    docs: HashMap::new(),
+  }
+  .run()?;
+  io.join()?;
+  OK
+}
@@ -0,0 +1,425 @@
+use std::{ops::Range, path::Path};
+
+use crate::{Edit, Options, project::Project};
+
+/// Source languages understood by [`crate::Mode::Code`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "conf", derive(serde::Deserialize))]
+#[cfg_attr(feature = "conf", serde(rename_all = "lowercase"))]
+pub enum Lang {
+  Rust,
+  /// JavaScript and TypeScript
+  #[cfg_attr(
+    feature = "conf",
+    serde(alias = "typescript", alias = "js", alias = "ts")
+  )]
+  JavaScript,
+  #[cfg_attr(feature = "conf", serde(alias = "py"))]
+  Python,
+  Go,
+}
+
+impl Lang {
+  pub const EXTENSIONS: &[(&str, Lang)] = &[
+    ("rs", Lang::Rust),
+    ("js", Lang::JavaScript),
+    ("jsx", Lang::JavaScript),
+    ("mjs", Lang::JavaScript),
+    ("cjs", Lang::JavaScript),
+    ("ts", Lang::JavaScript),
+    ("tsx", Lang::JavaScript),
+    ("mts", Lang::JavaScript),
+    ("cts", Lang::JavaScript),
+    ("py", Lang::Python),
+    ("pyi", Lang::Python),
+    ("go", Lang::Go),
+  ];
+
+  pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+    let ext = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+    Self::EXTENSIONS
+      .iter()
+      .find(|(e, _)| *e == ext)
+      .map(|(_, lang)| *lang)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+  /// Comment or doc comment body
+  Comment,
+  /// String literal body
+  Str,
+}
+
+/// Finds comment and string literal bodies, everything else is code.
+struct Scanner<'a> {
+  lang: Lang,
+  src: &'a [u8],
+  pos: usize,
+  spans: Vec<(Kind, Range<usize>)>,
+  /// Last significant byte of code, for JS regex literals and Python docstrings
+  last: Option<u8>,
+  /// Last identifier was a keyword that may precede an expression
+  last_keyword: bool,
+  /// Only whitespace since the start of the line
+  line_start: bool,
+}
+
+fn is_ident(b: u8) -> bool {
+  b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || b >= 0x80
+}
+
+impl<'a> Scanner<'a> {
+  fn new(lang: Lang, src: &'a str) -> Self {
+    Self {
+      lang,
+      src: src.as_bytes(),
+      pos: 0,
+      spans: Vec::new(),
+      last: None,
+      last_keyword: false,
+      line_start: true,
+    }
+  }
+
+  fn peek(&self, n: usize) -> Option<u8> {
+    self.src.get(self.pos + n).copied()
+  }
+
+  fn starts(&self, s: &str) -> bool {
+    self.src[self.pos..].starts_with(s.as_bytes())
+  }
+
+  fn find(&self, from: usize, s: &str) -> Option<usize> {
+    self.src[from..]
+      .windows(s.len())
+      .position(|w| w == s.as_bytes())
+      .map(|i| from + i)
+  }
+
+  fn line_end(&self, from: usize) -> usize {
+    self.src[from..]
+      .iter()
+      .position(|&b| b == b'\n')
+      .map_or(self.src.len(), |i| from + i)
+  }
+
+  fn span(&mut self, kind: Kind, range: Range<usize>) {
+    if !range.is_empty() {
+      self.spans.push((kind, range));
+    }
+  }
+
+  /// `//`, `#`, with doc markers like `///` and `//!` left out of the body.
+  fn line_comment(&mut self, marker: &str) {
+    let mut start = self.pos + marker.len();
+    while matches!(self.src.get(start), Some(b'/' | b'!' | b'#')) {
+      start += 1;
+    }
+    let end = self.line_end(start);
+    self.span(Kind::Comment, start.min(end)..end);
+    self.pos = end;
+  }
+
+  fn block_comment(&mut self, nested: bool) {
+    let start = self.pos + 2;
+    let mut depth = 0;
+    let mut i = start;
+    while i < self.src.len() {
+      if nested && self.src[i..].starts_with(b"/*") {
+        depth += 1;
+        i += 2;
+      } else if self.src[i..].starts_with(b"*/") {
+        if depth == 0 {
+          self.span(Kind::Comment, start..i);
+          self.pos = i + 2;
+          return;
+        }
+        depth -= 1;
+        i += 2;
+      } else {
+        i += 1;
+      }
+    }
+    self.span(Kind::Comment, start..i);
+    self.pos = i;
+  }
+
+  /// String closed by `quote`, `pos` is on the opening quote.
+  fn quoted(&mut self, kind: Kind, quote: &str, escape: bool, multiline: bool) {
+    let start = self.pos + quote.len();
+    let mut i = start;
+    while i < self.src.len() {
+      let b = self.src[i];
+      if escape && b == b'\\' {
+        i += 2;
+      } else if self.src[i..].starts_with(quote.as_bytes()) {
+        self.span(kind, start..i);
+        self.pos = i + quote.len();
+        return;
+      } else if b == b'\n' && !multiline {
+        break;
+      } else {
+        i += 1;
+      }
+    }
+    let i = i.min(self.src.len());
+    self.span(kind, start..i);
+    self.pos = i;
+  }
+
+  /// JS template literal, `${…}` inside is code.
+  fn template(&mut self) {
+    let mut start = self.pos + 1;
+    let mut i = start;
+    while i < self.src.len() {
+      match self.src[i] {
+        b'\\' => i += 2,
+        b'`' => {
+          self.span(Kind::Str, start..i);
+          self.pos = i + 1;
+          return;
+        }
+        b'$' if self.src.get(i + 1) == Some(&b'{') => {
+          self.span(Kind::Str, start..i);
+          self.pos = i + 2;
+          self.code(true);
+          i = self.pos;
+          start = i;
+        }
+        _ => i += 1,
+      }
+    }
+    let i = i.min(self.src.len());
+    self.span(Kind::Str, start..i);
+    self.pos = i;
+  }
+
+  /// Skips a JS regex literal, `pos` is on the opening `/`.
+  fn regex(&mut self) {
+    let mut i = self.pos + 1;
+    let mut class = false;
+    while let Some(&b) = self.src.get(i) {
+      match b {
+        b'\\' => i += 1,
+        b'[' => class = true,
+        b']' => class = false,
+        b'/' if !class => break,
+        b'\n' => break,
+        _ => {}
+      }
+      i += 1;
+    }
+    self.pos = (i + 1).min(self.src.len());
+  }
+
+  /// Skips a char or rune literal, or a Rust lifetime.
+  fn char_literal(&mut self) {
+    let rest = &self.src[self.pos + 1..];
+    let len = match rest.first() {
+      Some(b'\\') => rest
+        .get(2..)
+        .and_then(|r| r.iter().position(|&b| b == b'\'' || b == b'\n'))
+        .map(|i| i + 4),
+      Some(&b) => {
+        let c_len = match b {
+          0..0x80 => 1,
+          0xf0.. => 4,
+          0xe0.. => 3,
+          _ => 2,
+        };
+        (rest.get(c_len) == Some(&b'\'')).then_some(c_len + 2)
+      }
+      None => None,
+    };
+    self.pos += len.unwrap_or(1);
+  }
+
+  /// Python strings may be triple quoted, a triple quoted string on its own line is a docstring.
+  fn python_string(&mut self, quote: u8, line_start: bool) {
+    let triple = [quote; 3];
+    if self.src[self.pos..].starts_with(&triple) {
+      let doc = line_start
+        && !matches!(
+          self.last,
+          Some(b'(' | b'[' | b'{' | b',' | b'=' | b'+' | b'%')
+        );
+      let kind = if doc { Kind::Comment } else { Kind::Str };
+      let quote = std::str::from_utf8(&triple).unwrap();
+      self.quoted(kind, quote, true, true);
+    } else {
+      self.quoted(
+        Kind::Str,
+        if quote == b'"' { "\"" } else { "'" },
+        true,
+        false,
+      );
+    }
+  }
+
+  /// Rust raw string `r#"…"#`, `pos` is on the first `#` or `"`.
+  fn raw_string(&mut self) {
+    let hashes = self.src[self.pos..]
+      .iter()
+      .take_while(|&&b| b == b'#')
+      .count();
+    if self.peek(hashes) != Some(b'"') {
+      self.pos += hashes;
+      return;
+    }
+    let start = self.pos + hashes + 1;
+    let close = format!("\"{}", "#".repeat(hashes));
+    let end = self.find(start, &close).unwrap_or(self.src.len());
+    self.span(Kind::Str, start..end);
+    self.pos = (end + close.len()).min(self.src.len());
+  }
+
+  fn ident(&mut self, line_start: bool) {
+    let start = self.pos;
+    while self.peek(0).is_some_and(is_ident) {
+      self.pos += 1;
+    }
+    let word = &self.src[start..self.pos];
+    let next = self.peek(0);
+    match self.lang {
+      Lang::Rust if matches!(word, b"r" | b"br" | b"cr") && matches!(next, Some(b'"' | b'#')) => {
+        self.raw_string();
+      }
+      Lang::Rust if matches!(word, b"b" | b"c") && next == Some(b'"') => {
+        self.quoted(Kind::Str, "\"", true, true);
+      }
+      Lang::Rust if word == b"b" && next == Some(b'\'') => self.char_literal(),
+      Lang::Python
+        if word.len() <= 2
+          && word
+            .iter()
+            .all(|b| matches!(b.to_ascii_lowercase(), b'r' | b'b' | b'u' | b'f'))
+          && matches!(next, Some(b'"' | b'\'')) =>
+      {
+        self.python_string(next.unwrap(), line_start);
+      }
+      _ => {
+        self.last_keyword = matches!(
+          word,
+          b"return" | b"typeof" | b"case" | b"do" | b"else" | b"in" | b"of" | b"void" | b"yield"
+        );
+        self.last = word.last().copied();
+      }
+    }
+  }
+
+  /// Scans code, returning at an unmatched `}` when `brace` (the end of a template `${…}`).
+  fn code(&mut self, brace: bool) {
+    let mut depth = 0usize;
+    while let Some(b) = self.peek(0) {
+      let line_start = self.line_start;
+      self.line_start = b == b'\n' || (line_start && b.is_ascii_whitespace());
+      if b.is_ascii_whitespace() {
+        self.pos += 1;
+        continue;
+      }
+      let lang = self.lang;
+      match b {
+        b'/' if lang != Lang::Python && self.starts("//") => {
+          self.line_comment("//");
+          continue;
+        }
+        b'/' if lang != Lang::Python && self.starts("/*") => {
+          self.block_comment(lang == Lang::Rust);
+          continue;
+        }
+        b'#' if lang == Lang::Python => {
+          self.line_comment("#");
+          continue;
+        }
+        b'/'
+          if lang == Lang::JavaScript
+            && (self.last_keyword
+              || self
+                .last
+                .is_none_or(|l| b"(,=:[!&|?{};+-*%<>~^".contains(&l))) =>
+        {
+          self.regex();
+        }
+        b'"' => match lang {
+          Lang::Python => self.python_string(b, line_start),
+          _ => self.quoted(Kind::Str, "\"", true, lang == Lang::Rust),
+        },
+        b'\'' => match lang {
+          Lang::Python => self.python_string(b, line_start),
+          Lang::JavaScript => self.quoted(Kind::Str, "'", true, false),
+          Lang::Rust | Lang::Go => self.char_literal(),
+        },
+        b'`' if lang == Lang::JavaScript => self.template(),
+        b'`' if lang == Lang::Go => self.quoted(Kind::Str, "`", false, true),
+        _ if is_ident(b) => {
+          self.ident(line_start);
+          continue;
+        }
+        b'{' => {
+          depth += 1;
+          self.pos += 1;
+        }
+        b'}' => {
+          self.pos += 1;
+          if depth == 0 && brace {
+            return;
+          }
+          depth = depth.saturating_sub(1);
+        }
+        _ => self.pos += 1,
+      }
+      self.last = Some(self.src[self.pos - 1]);
+      self.last_keyword = false;
+    }
+  }
+}
+
+/// Projects comment bodies line by line, `` `code` `` in them is kept as is.
+fn comment(project: &mut Project, src: &str, range: Range<usize>) {
+  let mut start = range.start;
+  for line in src[range].split_inclusive('\n') {
+    let body = line.trim_end_matches(['\n', '\r']);
+    let mut rest = 0;
+    while let Some(open) = body[rest..].find('`').map(|i| rest + i) {
+      let Some(close) = body[open + 1..].find('`').map(|i| open + 1 + i) else {
+        break;
+      };
+      project.text(src, start + rest..start + open);
+      project.atom(start + open..start + close + 1);
+      rest = close + 1;
+    }
+    project.text(src, start + rest..start + body.len());
+    project.brk();
+    start += line.len();
+  }
+}
+
+fn string(project: &mut Project, src: &str, range: Range<usize>) {
+  let mut start = range.start;
+  for line in src[range].split_inclusive('\n') {
+    project.text(
+      src,
+      start..start + line.trim_end_matches(['\n', '\r']).len(),
+    );
+    project.brk();
+    start += line.len();
+  }
+}
+
+/// Spaces comments and doc comments of `lang` source, and string literals with
+/// [`Options::strings`], code is never touched.
+pub(crate) fn code_edits(txt: &str, lang: Lang, opts: &Options) -> Vec<Edit> {
+  let mut scanner = Scanner::new(lang, txt);
+  scanner.code(false);
+  let mut project = Project::default();
+  for (kind, range) in scanner.spans {
+    match kind {
+      Kind::Comment => comment(&mut project, txt, range),
+      Kind::Str if opts.strings => string(&mut project, txt, range),
+      Kind::Str => {}
+    }
+  }
+  project.edits(txt, opts)
+}
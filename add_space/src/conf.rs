@@ -0,0 +1,277 @@
+use std::{
+  collections::HashMap,
+  fs, mem,
+  path::{Path, PathBuf},
+};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::Deserialize;
+
+use regex::Regex;
+
+use crate::{Error, Mode, Options, Placeholder, Protect, Result, Script, ScriptRule};
+
+pub const CONF_FILE: &str = ".add_space.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawRule {
+  before_word: Option<bool>,
+  after_word: Option<bool>,
+  fullwidth_punctuation: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Raw {
+  scripts: Option<Vec<String>>,
+  /// script name -> rule
+  script_rules: HashMap<String, RawRule>,
+  fullwidth_digit: Option<bool>,
+  punctuation: Option<String>,
+  emoji: Option<bool>,
+  space_after_punctuation: Option<bool>,
+  space_after_period: Option<bool>,
+  fullwidth_punctuation: Option<bool>,
+  halfwidth_punctuation: Option<bool>,
+  halfwidth_alphanumeric: Option<bool>,
+  unit_space: Option<bool>,
+  /// added to the built-in units
+  units: Vec<String>,
+  strings: Option<bool>,
+  html_attributes: Option<Vec<String>>,
+  keys: Option<Vec<String>>,
+  skip_keys: Option<Vec<String>>,
+  placeholders: Option<Vec<Placeholder>>,
+  placeholder_space: Option<bool>,
+  /// built-in kinds, all when missing
+  protect: Option<Vec<Protect>>,
+  protect_regex: Vec<String>,
+  remove_space: Option<bool>,
+  include: Vec<String>,
+  exclude: Vec<String>,
+  /// file extension -> mode
+  mode: HashMap<String, Mode>,
+}
+
+/// Copies the fields given in `src` to `dst`.
+macro_rules! set {
+  ($dst:ident, $src:ident: $($field:ident),*) => {
+    $(
+      if let Some(on) = $src.$field {
+        $dst.$field = on;
+      }
+    )*
+  };
+}
+
+fn script(name: String) -> Result<Script> {
+  Script::from_full_name(&name)
+    .or_else(|| Script::from_short_name(&name))
+    .ok_or(Error::Script(name))
+}
+
+fn glob_set(globs: &[String]) -> Result<GlobSet> {
+  let mut builder = GlobSetBuilder::new();
+  for glob in globs {
+    builder.add(Glob::new(glob)?);
+  }
+  Ok(builder.build()?)
+}
+
+impl Mode {
+  /// Mode named like the values of the `mode` table: `text`, `markdown`, `html`, a language,
+  /// `rust`, `ts`, `py`, `go`, a subtitle format, `srt`, `vtt`, `ass`, a data format, `json`,
+  /// `yaml`, `toml`, or a translation format, `po`, `xliff`.
+  #[cfg(any(feature = "ffi", feature = "wasm"))]
+  pub(crate) fn from_name(name: &str) -> Result<Self> {
+    use serde::de::IntoDeserializer;
+
+    Self::deserialize(name.to_ascii_lowercase().into_deserializer())
+      .map_err(|_: serde::de::value::Error| Error::Mode(name.into()))
+  }
+}
+
+/// Project configuration, read from `.add_space.toml` or the `[package.metadata.add_space]` /
+/// `[workspace.metadata.add_space]` table of a `Cargo.toml`.
+#[derive(Debug, Default)]
+pub struct Conf {
+  /// Directory holding the configuration file, include / exclude globs are relative to it
+  pub root: PathBuf,
+  pub options: Options,
+  include: Option<GlobSet>,
+  exclude: GlobSet,
+  mode: HashMap<String, Mode>,
+}
+
+impl Raw {
+  /// Options of the table, the file selection keys are left in place.
+  pub(crate) fn options(&mut self) -> Result<Options> {
+    let mut options = Options::default();
+    if let Some(scripts) = self.scripts.take() {
+      options.scripts = scripts.into_iter().map(script).collect::<Result<_>>()?;
+    }
+    for (name, raw_rule) in mem::take(&mut self.script_rules) {
+      let script = script(name)?;
+      let mut rule = options
+        .script_rules
+        .iter()
+        .find(|(s, _)| *s == script)
+        .map_or(ScriptRule::CJK, |(_, rule)| *rule);
+      set!(rule, raw_rule: before_word, after_word, fullwidth_punctuation);
+      options = options.script_rule(script, rule);
+    }
+    if let Some(punctuation) = self.punctuation.take() {
+      options.punctuation = punctuation;
+    }
+    options.unit_space = self.unit_space.or(options.unit_space);
+    options.placeholder_space = self.placeholder_space.or(options.placeholder_space);
+    options.units.append(&mut self.units);
+    if let Some(names) = self.html_attributes.take() {
+      options.html_attributes = names;
+    }
+    if let Some(keys) = self.keys.take() {
+      options.keys = keys;
+    }
+    if let Some(keys) = self.skip_keys.take() {
+      options.skip_keys = keys;
+    }
+    if let Some(placeholders) = self.placeholders.take() {
+      options.placeholders = placeholders;
+    }
+    if let Some(protect) = self.protect.take() {
+      options.protect = protect;
+    }
+    for re in mem::take(&mut self.protect_regex) {
+      options = options.protect_regex(Regex::new(&re)?);
+    }
+    set!(
+      options, self:
+      fullwidth_digit,
+      emoji,
+      space_after_punctuation,
+      space_after_period,
+      fullwidth_punctuation,
+      halfwidth_punctuation,
+      halfwidth_alphanumeric,
+      strings,
+      remove_space
+    );
+    Ok(options)
+  }
+}
+
+impl Conf {
+  fn new(root: PathBuf, mut raw: Raw) -> Result<Self> {
+    let options = raw.options()?;
+
+    Ok(Self {
+      root,
+      options,
+      include: if raw.include.is_empty() {
+        None
+      } else {
+        Some(glob_set(&raw.include)?)
+      },
+      exclude: glob_set(&raw.exclude)?,
+      mode: raw
+        .mode
+        .into_iter()
+        .map(|(ext, mode)| (ext.trim_start_matches('.').to_ascii_lowercase(), mode))
+        .collect(),
+    })
+  }
+
+  /// Loads a configuration file, `None` when it is a `Cargo.toml` without an `add_space` table.
+  pub fn load(path: impl AsRef<Path>) -> Result<Option<Self>> {
+    let path = path.as_ref();
+    let txt = fs::read_to_string(path)?;
+    let err = |e| Error::Toml(path.into(), e);
+    let root = fs::canonicalize(path)?
+      .parent()
+      .map(Path::to_path_buf)
+      .unwrap_or_default();
+
+    if path.file_name().is_some_and(|name| name == "Cargo.toml") {
+      let manifest: toml::Table = toml::from_str(&txt).map_err(err)?;
+      for section in ["package", "workspace"] {
+        if let Some(value) = manifest
+          .get(section)
+          .and_then(|v| v.get("metadata"))
+          .and_then(|v| v.get("add_space"))
+        {
+          let raw: Raw = value.clone().try_into().map_err(err)?;
+          return Self::new(root, raw).map(Some);
+        }
+      }
+      return Ok(None);
+    }
+
+    Self::new(root, toml::from_str(&txt).map_err(err)?).map(Some)
+  }
+
+  /// Walks up from `path` (a file or a directory) to the nearest configuration.
+  pub fn find(path: impl AsRef<Path>) -> Result<Option<Self>> {
+    let path = path.as_ref();
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.into());
+    let start = if path.is_dir() {
+      Some(path.as_path())
+    } else {
+      path.parent()
+    };
+    for dir in start.into_iter().flat_map(Path::ancestors) {
+      for name in [CONF_FILE, "Cargo.toml"] {
+        let file = dir.join(name);
+        if file.is_file()
+          && let Some(conf) = Self::load(&file)?
+        {
+          return Ok(Some(conf));
+        }
+      }
+    }
+    Ok(None)
+  }
+
+  fn rel(&self, path: &Path) -> PathBuf {
+    let abs = fs::canonicalize(path).unwrap_or_else(|_| path.into());
+    abs
+      .strip_prefix(&self.root)
+      .map(Path::to_path_buf)
+      .unwrap_or(abs)
+  }
+
+  /// Whether `path` passes the include / exclude globs.
+  pub fn is_included(&self, path: impl AsRef<Path>) -> bool {
+    let rel = self.rel(path.as_ref());
+    !self.exclude.is_match(&rel) && self.include.as_ref().is_none_or(|g| g.is_match(&rel))
+  }
+
+  /// Whether walking a directory picks `path`: the include globs match it, or its extension has a
+  /// mode, from the `mode` table or [`Mode::from_known_path`].
+  pub fn is_walked(&self, path: impl AsRef<Path>) -> bool {
+    let path = path.as_ref();
+    if self
+      .include
+      .as_ref()
+      .is_some_and(|g| g.is_match(self.rel(path)))
+    {
+      return true;
+    }
+    let configured = path
+      .extension()
+      .and_then(|ext| ext.to_str())
+      .is_some_and(|ext| self.mode.contains_key(&ext.to_ascii_lowercase()));
+    configured || Mode::from_known_path(path).is_some()
+  }
+
+  /// Mode for `path`, the configured extension mapping first, then [`Mode::from_path`].
+  pub fn mode(&self, path: impl AsRef<Path>) -> Mode {
+    let path = path.as_ref();
+    path
+      .extension()
+      .and_then(|ext| ext.to_str())
+      .and_then(|ext| self.mode.get(&ext.to_ascii_lowercase()))
+      .copied()
+      .unwrap_or_else(|| Mode::from_path(path))
+  }
+}
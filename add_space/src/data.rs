@@ -0,0 +1,528 @@
+use std::{ops::Range, path::Path};
+
+use crate::{Edit, Options, Placeholder, project::Project, subtitle::lines};
+
+/// Structured data formats understood by [`crate::Mode::Data`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "conf", derive(serde::Deserialize))]
+#[cfg_attr(feature = "conf", serde(rename_all = "lowercase"))]
+pub enum Data {
+  /// JSON, `//` and `/* */` comments allowed
+  #[cfg_attr(feature = "conf", serde(alias = "jsonc"))]
+  Json,
+  #[cfg_attr(feature = "conf", serde(alias = "yml"))]
+  Yaml,
+  Toml,
+}
+
+impl Data {
+  pub const EXTENSIONS: &[(&str, Data)] = &[
+    ("json", Data::Json),
+    ("jsonc", Data::Json),
+    ("yaml", Data::Yaml),
+    ("yml", Data::Yaml),
+    ("toml", Data::Toml),
+  ];
+
+  pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+    let ext = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+    Self::EXTENSIONS
+      .iter()
+      .find(|(e, _)| *e == ext)
+      .map(|(_, data)| *data)
+  }
+}
+
+/// How a string value is written, which decides its escapes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Style {
+  /// `"…"`, backslash escapes
+  Escaped,
+  /// `'…'` in TOML and YAML block scalars, no escapes
+  Literal,
+  /// `'…'` in YAML, `''` is a quote
+  Quoted,
+  /// YAML plain scalar, no escapes, but `: ` and ` #` would end it
+  Plain,
+}
+
+/// Whether `pattern`, dotted keys with `*` for any one key, names keys of `path` ending at some
+/// depth: `title` and `home.title` both match `home.title.short`.
+fn matches(pattern: &str, path: &[String]) -> bool {
+  let pattern: Vec<&str> = pattern.split('.').collect();
+  (pattern.len()..=path.len()).any(|end| {
+    path[end - pattern.len()..end]
+      .iter()
+      .zip(&pattern)
+      .all(|(key, p)| *p == "*" || key == p)
+  })
+}
+
+/// `\n`, `\"`, `\u4e2d` and the like at the start of `rest`, as the char written and its length.
+pub(crate) fn escape(rest: &str) -> Option<(char, usize)> {
+  let hex = |range: Range<usize>| {
+    rest
+      .get(range)
+      .and_then(|h| u32::from_str_radix(h, 16).ok())
+  };
+  let c = match *rest.as_bytes().get(1)? {
+    b'u' => {
+      let c = hex(2..6)?;
+      // a UTF-16 surrogate pair, as JSON writes chars past the BMP
+      if (0xd800..0xdc00).contains(&c) && rest.get(6..8) == Some("\\u") {
+        let low = hex(8..12)?.checked_sub(0xdc00).filter(|low| *low < 0x400)?;
+        let c = char::from_u32(0x10000 + ((c - 0xd800) << 10) + low);
+        return Some((c.unwrap_or('\u{fffd}'), 12));
+      }
+      return Some((char::from_u32(c).unwrap_or('\u{fffd}'), 6));
+    }
+    b'U' => return Some((char::from_u32(hex(2..10)?).unwrap_or('\u{fffd}'), 10)),
+    b'x' => return Some((char::from_u32(hex(2..4)?).unwrap_or('\u{fffd}'), 4)),
+    b'_' => '\u{a0}',
+    b'n' | b't' | b'r' | b'b' | b'f' | b'v' | b'a' | b'e' | b'0' | b'N' | b'L' | b'P' => '\n',
+    // a line continuation
+    b'\n' | b'\r' => ' ',
+    c if c.is_ascii() => c as char,
+    // kept whole, a width fix must not turn it into another escape
+    _ => {
+      let c = rest[1..].chars().next()?;
+      return Some((c, 1 + c.len_utf8()));
+    }
+  };
+  Some((c, 2))
+}
+
+/// Content of the string whose opening quote is at `b[start]`, up to the closing quote or the end.
+pub(crate) fn string(b: &[u8], start: usize, style: Style) -> Range<usize> {
+  let quote = b[start];
+  let mut i = start + 1;
+  while i < b.len() {
+    match b[i] {
+      b'\\' if style == Style::Escaped => i += 1,
+      c if c == quote && style == Style::Quoted && b.get(i + 1) == Some(&quote) => i += 1,
+      c if c == quote => return start + 1..i,
+      _ => {}
+    }
+    i += 1;
+  }
+  start + 1..b.len()
+}
+
+/// Content of the TOML multi-line string whose opening `"""` or `'''` is at `b[start]`.
+fn multiline(b: &[u8], start: usize, style: Style) -> Range<usize> {
+  let quote = b[start];
+  let mut i = start + 3;
+  while i < b.len() {
+    if b[i] == b'\\' && style == Style::Escaped {
+      i += 2;
+      continue;
+    }
+    if b[i..].starts_with(&[quote; 3]) {
+      // up to two quotes may come right before the closing ones
+      let run = b[i..].iter().take_while(|c| **c == quote).count();
+      return start + 3..i + run.min(5) - 3;
+    }
+    i += 1;
+  }
+  start + 3..b.len()
+}
+
+/// Offset of the end of the line `i` is in.
+fn line_end(b: &[u8], i: usize) -> usize {
+  b[i..]
+    .iter()
+    .position(|&c| c == b'\n')
+    .map_or(b.len(), |n| i + n)
+}
+
+/// String values of a document, with the keys leading to them.
+struct Values<'a> {
+  src: &'a str,
+  opts: &'a Options,
+  project: Project,
+  /// YAML plain scalars, where edits could change the structure
+  plain: Vec<Range<usize>>,
+}
+
+impl Values<'_> {
+  /// The string value at `range` under `path`, spaced when [`Options::keys`] and
+  /// [`Options::skip_keys`] select it. Escapes are spaced as the char they stand for.
+  fn value(&mut self, path: &[String], range: Range<usize>, style: Style) {
+    let opts = self.opts;
+    if !(opts.keys.is_empty() || opts.keys.iter().any(|k| matches(k, path)))
+      || opts.skip_keys.iter().any(|k| matches(k, path))
+    {
+      return;
+    }
+    if style == Style::Plain {
+      self.plain.push(range.clone());
+    }
+    let src = self.src;
+    let b = src.as_bytes();
+    let mut start = range.start;
+    let mut i = start;
+    while i < range.end {
+      // tokens start with ASCII bytes, so `i` is on a char boundary when one is found
+      let rest = || &src[i..range.end];
+      let token = match b[i] {
+        b'\\' if style == Style::Escaped => escape(rest()),
+        b'\'' if style == Style::Quoted && rest().starts_with("''") => Some(('\'', 2)),
+        _ => None,
+      };
+      let Some((c, len)) = token else {
+        i += 1;
+        continue;
+      };
+      self.project.text(src, start..i);
+      self.project.escaped(c, i..i + len);
+      i += len;
+      start = i;
+    }
+    self.project.text(src, start..range.end);
+    self.project.brk();
+  }
+
+  /// Whether `e` keeps the plain scalars it touches plain: no `: ` or ` #`, which start a mapping
+  /// or a comment, and no indicator like `!` or `&` up front.
+  fn keeps_plain(&self, e: &Edit) -> bool {
+    let b = self.src.as_bytes();
+    let Some(plain) = self
+      .plain
+      .iter()
+      .find(|p| p.start <= e.range.start && e.range.end <= p.end)
+    else {
+      return true;
+    };
+    if e.text.contains([':', '#']) {
+      return false;
+    }
+    if e.range.start == plain.start && e.text.starts_with(|c| "-?,[]{}!&*|>'\"%@`".contains(c)) {
+      return false;
+    }
+    let comment = e.text.ends_with(' ') && b.get(e.range.end) == Some(&b'#');
+    let mapping = e.text.starts_with(' ') && e.range.start > 0 && b[e.range.start - 1] == b':';
+    !comment && !mapping
+  }
+}
+
+/// Keys of the enclosing containers followed by `key`.
+fn path(stack: &[Option<String>], key: Option<&String>) -> Vec<String> {
+  stack.iter().flatten().chain(key).cloned().collect()
+}
+
+fn json(v: &mut Values) {
+  let src = v.src;
+  let b = src.as_bytes();
+  // enclosing objects and arrays, with the key they are the value of
+  let mut objects = Vec::new();
+  let mut stack = Vec::new();
+  let mut key = None;
+  let mut i = 0;
+  while i < b.len() {
+    match b[i] {
+      b'"' => {
+        let s = string(b, i, Style::Escaped);
+        i = (s.end + 1).min(b.len());
+        let colon = b[i..].iter().find(|c| !c.is_ascii_whitespace()) == Some(&b':');
+        if objects.last() == Some(&true) && colon {
+          key = Some(src[s].to_string());
+        } else {
+          v.value(&path(&stack, key.as_ref()), s, Style::Escaped);
+        }
+        continue;
+      }
+      b'{' | b'[' => {
+        objects.push(b[i] == b'{');
+        stack.push(key.take());
+      }
+      b'}' | b']' => {
+        objects.pop();
+        stack.pop();
+        key = None;
+      }
+      b',' => key = None,
+      b'/' if b.get(i + 1) == Some(&b'/') => i = line_end(b, i),
+      b'/' if b.get(i + 1) == Some(&b'*') => {
+        i = src[i + 2..].find("*/").map_or(b.len(), |n| i + n + 3);
+      }
+      _ => {}
+    }
+    i += 1;
+  }
+}
+
+/// Offset past the spaces at `i`, also past newlines and `#` comments when `lines`.
+fn skip(b: &[u8], mut i: usize, lines: bool) -> usize {
+  while i < b.len() {
+    match b[i] {
+      b' ' | b'\t' => i += 1,
+      b'\r' | b'\n' if lines => i += 1,
+      b'#' if lines => i = line_end(b, i),
+      _ => break,
+    }
+  }
+  i
+}
+
+/// The dotted TOML key at `i`, `site."zh-CN".title`, and the offset past it.
+fn toml_keys(src: &str, mut i: usize) -> (Vec<String>, usize) {
+  let b = src.as_bytes();
+  let mut keys = Vec::new();
+  loop {
+    i = skip(b, i, false);
+    match b.get(i) {
+      Some(b'"' | b'\'') => {
+        let style = if b[i] == b'"' {
+          Style::Escaped
+        } else {
+          Style::Literal
+        };
+        let s = string(b, i, style);
+        i = (s.end + 1).min(b.len());
+        keys.push(src[s].to_string());
+      }
+      _ => {
+        let len = b[i..]
+          .iter()
+          .take_while(|c| c.is_ascii_alphanumeric() || **c == b'_' || **c == b'-')
+          .count();
+        if len == 0 {
+          break;
+        }
+        keys.push(src[i..i + len].to_string());
+        i += len;
+      }
+    }
+    i = skip(b, i, false);
+    if b.get(i) != Some(&b'.') {
+      break;
+    }
+    i += 1;
+  }
+  (keys, i)
+}
+
+/// The TOML value at `i` under `path`, returns the offset past it.
+fn toml_value(v: &mut Values, path: &[String], i: usize) -> usize {
+  let src = v.src;
+  let b = src.as_bytes();
+  let mut i = skip(b, i, false);
+  match b.get(i) {
+    Some(&quote @ (b'"' | b'\'')) => {
+      let style = if quote == b'"' {
+        Style::Escaped
+      } else {
+        Style::Literal
+      };
+      let (s, len) = if b[i..].starts_with(&[quote; 3]) {
+        (multiline(b, i, style), 3)
+      } else {
+        (string(b, i, style), 1)
+      };
+      let end = (s.end + len).min(b.len());
+      v.value(path, s, style);
+      end
+    }
+    Some(b'[') => {
+      i += 1;
+      loop {
+        i = skip(b, i, true);
+        match b.get(i) {
+          None => return i,
+          Some(b']') => return i + 1,
+          Some(b',') => i += 1,
+          _ => i = toml_value(v, path, i).max(i + 1),
+        }
+      }
+    }
+    Some(b'{') => {
+      i += 1;
+      loop {
+        i = skip(b, i, true);
+        match b.get(i) {
+          None => return i,
+          Some(b'}') => return i + 1,
+          Some(b',') => i += 1,
+          _ => {
+            let (keys, j) = toml_keys(src, i);
+            if b.get(j) != Some(&b'=') {
+              return line_end(b, i);
+            }
+            let path: Vec<String> = path.iter().cloned().chain(keys).collect();
+            i = toml_value(v, &path, j + 1).max(j + 1);
+          }
+        }
+      }
+    }
+    _ => {
+      let len = b[i..]
+        .iter()
+        .take_while(|c| !b",]}#\r\n".contains(c))
+        .count();
+      i + len
+    }
+  }
+}
+
+fn toml(v: &mut Values) {
+  let src = v.src;
+  let b = src.as_bytes();
+  let mut table = Vec::new();
+  let mut i = 0;
+  while i < b.len() {
+    i = skip(b, i, true);
+    if i >= b.len() {
+      break;
+    }
+    if b[i] == b'[' {
+      let start = if b.get(i + 1) == Some(&b'[') {
+        i + 2
+      } else {
+        i + 1
+      };
+      table = toml_keys(src, start).0;
+      i = line_end(b, i);
+      continue;
+    }
+    let (keys, j) = toml_keys(src, i);
+    if b.get(j) != Some(&b'=') {
+      i = line_end(b, i);
+      continue;
+    }
+    let path: Vec<String> = table.iter().cloned().chain(keys).collect();
+    i = toml_value(v, &path, j + 1);
+  }
+}
+
+/// The mapping key `rest` starts with, `title:` or `"title":`, and the length up to the colon.
+fn yaml_key(rest: &str) -> Option<(String, usize)> {
+  let b = rest.as_bytes();
+  let (key, end) = match b.first()? {
+    b'"' | b'\'' => {
+      let style = if b[0] == b'"' {
+        Style::Escaped
+      } else {
+        Style::Quoted
+      };
+      let s = string(b, 0, style);
+      let end = s.end + 1 + skip(&b[(s.end + 1).min(b.len())..], 0, false);
+      (&rest[s], end)
+    }
+    b'{' | b'[' | b'#' | b'|' | b'>' | b'&' | b'*' | b'!' => return None,
+    _ => {
+      let comment = rest.find(" #").unwrap_or(rest.len());
+      let end = rest[..comment]
+        .match_indices(':')
+        .map(|(i, _)| i)
+        .find(|i| matches!(b.get(i + 1), None | Some(b' ' | b'\t')))?;
+      (rest[..end].trim_end(), end)
+    }
+  };
+  (b.get(end) == Some(&b':')).then(|| (key.to_string(), end + 1))
+}
+
+/// Block YAML: mappings and sequences, scalars plain, quoted or block. Flow collections are left
+/// alone.
+fn yaml(v: &mut Values) {
+  let src = v.src;
+  let b = src.as_bytes();
+  let lines: Vec<_> = lines(src).collect();
+  // keys of the mappings the line is in, with their column
+  let mut stack: Vec<(usize, String)> = Vec::new();
+  let mut n = 0;
+  while n < lines.len() {
+    let line = lines[n].clone();
+    n += 1;
+    let s = &src[line.clone()];
+    let t = s.trim();
+    if t.is_empty() || t.starts_with(['#', '%']) || t.starts_with("---") || t.starts_with("...") {
+      continue;
+    }
+    let indent = s.len() - s.trim_start_matches(' ').len();
+    let mut at = line.start + indent;
+    // `- ` sequence entries, their content is indented past the dash
+    while at < line.end && b[at] == b'-' && (at + 1 == line.end || b[at + 1] == b' ') {
+      at = skip(b, at + 1, false).min(line.end);
+    }
+    let col = at - line.start;
+    let key = yaml_key(&src[at..line.end]);
+    let parent = match &key {
+      Some((key, len)) => {
+        while stack.last().is_some_and(|(c, _)| *c >= col) {
+          stack.pop();
+        }
+        stack.push((col, key.clone()));
+        at = skip(b, at + len, false).min(line.end);
+        col
+      }
+      None => {
+        while stack.last().is_some_and(|(c, _)| *c > indent) {
+          stack.pop();
+        }
+        indent
+      }
+    };
+    let path: Vec<String> = stack.iter().map(|(_, k)| k.clone()).collect();
+    // tags and anchors
+    while at < line.end && matches!(b[at], b'!' | b'&') {
+      let len = b[at..line.end].iter().take_while(|c| **c != b' ').count();
+      at = skip(b, at + len, false);
+    }
+    if at >= line.end {
+      continue;
+    }
+    match b[at] {
+      b'#' | b'*' | b'{' | b'[' => {}
+      b'|' | b'>' => {
+        while let Some(next) = lines.get(n) {
+          let s = &src[next.clone()];
+          let indent = s.len() - s.trim_start_matches(' ').len();
+          if !s.trim().is_empty() && indent <= parent {
+            break;
+          }
+          if !s.trim().is_empty() {
+            v.value(&path, next.start + indent..next.end, Style::Literal);
+          }
+          n += 1;
+        }
+      }
+      quote @ (b'"' | b'\'') => {
+        let style = if quote == b'"' {
+          Style::Escaped
+        } else {
+          Style::Quoted
+        };
+        let s = string(b, at, style);
+        // a quoted scalar may go on over the next lines
+        while lines.get(n).is_some_and(|l| l.start < s.end) {
+          n += 1;
+        }
+        v.value(&path, s, style);
+      }
+      _ => {
+        let rest = &src[at..line.end];
+        let end = rest.find(" #").unwrap_or(rest.len());
+        v.value(&path, at..at + rest[..end].trim_end().len(), Style::Plain);
+      }
+    }
+  }
+}
+
+pub(crate) fn data_edits(src: &str, data: Data, opts: &Options) -> Vec<Edit> {
+  // ICU placeholders like `{count}` are kept whatever the options
+  let opts = &opts.clone().placeholder(Placeholder::Icu);
+  let mut values = Values {
+    src,
+    opts,
+    project: Project::default(),
+    plain: Vec::new(),
+  };
+  match data {
+    Data::Json => json(&mut values),
+    Data::Yaml => yaml(&mut values),
+    Data::Toml => toml(&mut values),
+  }
+  let mut r = values.project.edits(src, opts);
+  r.retain(|e| values.keeps_plain(e));
+  r
+}
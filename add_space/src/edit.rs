@@ -0,0 +1,103 @@
+use std::{fmt, ops::Range};
+
+/// Rule behind an [`Edit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Rule {
+  /// CJK followed by a word, `中文English`
+  CjkWord,
+  /// Word followed by CJK, `English中文`
+  WordCjk,
+  /// Word after `,?!…`
+  Punctuation,
+  /// Capitalized word after `.`
+  Period,
+  /// Half-width punctuation in CJK text, `中文,中文`
+  FullwidthPunctuation,
+  /// Full-width punctuation in Latin text, `word，word`
+  HalfwidthPunctuation,
+  /// Full-width letter or digit, `Ａ１`
+  HalfwidthAlphanumeric,
+  /// Space between a number and its unit, `10GB` / `10 GB`, `25 %`
+  Unit,
+  /// Space inside CJK text or next to full-width punctuation, `中 文 ，`
+  ExtraSpace,
+}
+
+impl fmt::Display for Rule {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      Rule::CjkWord | Rule::WordCjk => "missing space between CJK and Latin text",
+      Rule::Punctuation => "missing space after punctuation",
+      Rule::Period => "missing space after period",
+      Rule::FullwidthPunctuation => "half-width punctuation in CJK text",
+      Rule::HalfwidthPunctuation => "full-width punctuation in Latin text",
+      Rule::HalfwidthAlphanumeric => "full-width letter or digit",
+      Rule::Unit => "inconsistent spacing between number and unit",
+      Rule::ExtraSpace => "extra space in CJK text",
+    })
+  }
+}
+
+/// A change to the original text: `range` (bytes) is replaced by `text`, an insertion has an empty
+/// range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+  pub range: Range<usize>,
+  /// `range` counted in chars
+  pub char_range: Range<usize>,
+  pub text: String,
+  pub rule: Rule,
+}
+
+impl Edit {
+  /// Space before byte `offset`, `char_range` is filled by [`set_char_range`].
+  pub(crate) fn space(offset: usize, rule: Rule) -> Self {
+    Self {
+      range: offset..offset,
+      char_range: 0..0,
+      text: " ".into(),
+      rule,
+    }
+  }
+
+  /// `range` replaced by `text`.
+  pub(crate) fn replace(range: Range<usize>, text: impl Into<String>, rule: Rule) -> Self {
+    Self {
+      range,
+      char_range: 0..0,
+      text: text.into(),
+      rule,
+    }
+  }
+}
+
+/// Sorts `edits` and fills in their `char_range` from the byte ranges.
+pub(crate) fn set_char_range(txt: &str, edits: &mut [Edit]) {
+  edits.sort_by_key(|e| (e.range.start, e.range.end));
+  let mut byte = 0;
+  let mut chars = 0;
+  let mut count = |to: usize| {
+    chars += txt[byte..to].chars().count();
+    byte = to;
+    chars
+  };
+  for edit in edits {
+    let start = count(edit.range.start);
+    let end = count(edit.range.end);
+    edit.char_range = start..end;
+  }
+}
+
+/// Applies edits sorted by position and not overlapping, as returned by [`crate::edits`].
+pub fn apply_edits(txt: &str, edits: &[Edit]) -> String {
+  let mut r = String::with_capacity(txt.len() + edits.len());
+  let mut pre = 0;
+  for edit in edits {
+    r.push_str(&txt[pre..edit.range.start]);
+    r.push_str(&edit.text);
+    pre = edit.range.end;
+  }
+  r.push_str(&txt[pre..]);
+  r
+}
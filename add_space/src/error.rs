@@ -0,0 +1,27 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+  #[error(transparent)]
+  Io(#[from] std::io::Error),
+
+  #[cfg(feature = "conf")]
+  #[error("{0}: {1}")]
+  Toml(std::path::PathBuf, toml::de::Error),
+
+  #[cfg(feature = "conf")]
+  #[error(transparent)]
+  Glob(#[from] globset::Error),
+
+  #[error(transparent)]
+  Regex(#[from] regex::Error),
+
+  #[error("unknown script: {0}")]
+  Script(String),
+
+  #[cfg(feature = "conf")]
+  #[error("unknown mode: {0}")]
+  Mode(String),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,133 @@
+//! C ABI, declared in `include/add_space.h`. Strings in and out are NUL-terminated UTF-8, those
+//! returned are owned by the caller and released with [`add_space_free`].
+
+use std::{
+  ffi::{CStr, CString, c_char},
+  ptr,
+};
+
+use crate::{Mode, Options, conf::Raw};
+
+/// Parsed options, reused across calls.
+pub struct AddSpaceOptions(Options);
+
+/// `None` for a NULL pointer or invalid UTF-8.
+unsafe fn str_of<'a>(s: *const c_char) -> Option<&'a str> {
+  if s.is_null() {
+    return None;
+  }
+  unsafe { CStr::from_ptr(s) }.to_str().ok()
+}
+
+/// `s` handed over to C, it holds no NUL as it comes from a C string.
+fn owned(s: String) -> *mut c_char {
+  CString::new(s).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Formats `txt` with the default options, NULL when `txt` is NULL or not UTF-8.
+///
+/// # Safety
+///
+/// `txt` is NULL or a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn add_space(txt: *const c_char) -> *mut c_char {
+  unsafe { str_of(txt) }.map_or(ptr::null_mut(), |txt| owned(crate::add_space(txt)))
+}
+
+/// Like [`add_space`], also removing spurious spaces from CJK text.
+///
+/// # Safety
+///
+/// `txt` is NULL or a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn add_space_normalize(txt: *const c_char) -> *mut c_char {
+  unsafe { str_of(txt) }.map_or(ptr::null_mut(), |txt| owned(crate::normalize(txt)))
+}
+
+/// Options from TOML with the keys of `.add_space.toml`, the defaults when `toml` is NULL. On
+/// error returns NULL and, when `err` is not NULL, stores the message there, to be released with
+/// [`add_space_free`].
+///
+/// # Safety
+///
+/// `toml` is NULL or a NUL-terminated string, `err` is NULL or writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn add_space_options_new(
+  toml: *const c_char,
+  err: *mut *mut c_char,
+) -> *mut AddSpaceOptions {
+  let options = if toml.is_null() {
+    Ok(Options::default())
+  } else {
+    match unsafe { str_of(toml) } {
+      Some(toml) => toml::from_str::<Raw>(toml)
+        .map_err(|e| e.to_string())
+        .and_then(|mut raw| raw.options().map_err(|e| e.to_string())),
+      None => Err("options are not UTF-8".into()),
+    }
+  };
+  match options {
+    Ok(options) => Box::into_raw(Box::new(AddSpaceOptions(options))),
+    Err(e) => {
+      if !err.is_null() {
+        unsafe { *err = owned(e) };
+      }
+      ptr::null_mut()
+    }
+  }
+}
+
+/// Releases options from [`add_space_options_new`], NULL is ignored.
+///
+/// # Safety
+///
+/// `opts` is NULL or from [`add_space_options_new`], and not used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn add_space_options_free(opts: *mut AddSpaceOptions) {
+  if !opts.is_null() {
+    drop(unsafe { Box::from_raw(opts) });
+  }
+}
+
+/// Formats `txt` read as `mode`: `text` (when NULL), `markdown`, `html`, a language, `rust`,
+/// `ts`, `py`, `go`, a subtitle format, `srt`, `vtt`, `ass`, a data format, `json`, `yaml`,
+/// `toml`, or a translation format, `po`, `xliff`. `opts` may be NULL for the defaults. NULL when
+/// `txt` is NULL or not UTF-8, or the mode is unknown.
+///
+/// # Safety
+///
+/// `opts` is NULL or from [`add_space_options_new`], `txt` and `mode` are NULL or NUL-terminated
+/// strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn add_space_format(
+  opts: *const AddSpaceOptions,
+  txt: *const c_char,
+  mode: *const c_char,
+) -> *mut c_char {
+  let mode = if mode.is_null() {
+    Mode::Text
+  } else {
+    match unsafe { str_of(mode) }.map(Mode::from_name) {
+      Some(Ok(mode)) => mode,
+      _ => return ptr::null_mut(),
+    }
+  };
+  let Some(txt) = (unsafe { str_of(txt) }) else {
+    return ptr::null_mut();
+  };
+  let default = Options::default();
+  let opts = unsafe { opts.as_ref() }.map_or(&default, |o| &o.0);
+  owned(mode.format(txt, opts))
+}
+
+/// Releases a string returned by this library, NULL is ignored.
+///
+/// # Safety
+///
+/// `s` is NULL or returned by this library, and not used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn add_space_free(s: *mut c_char) {
+  if !s.is_null() {
+    drop(unsafe { CString::from_raw(s) });
+  }
+}
@@ -0,0 +1,266 @@
+use std::{ops::Range, path::Path};
+
+use crate::{Edit, Options, project::Project};
+
+pub(crate) fn is_html(path: impl AsRef<Path>) -> bool {
+  path
+    .as_ref()
+    .extension()
+    .and_then(|ext| ext.to_str())
+    .is_some_and(|ext| matches!(ext.to_ascii_lowercase().as_str(), "html" | "htm" | "xhtml"))
+}
+
+/// Phrasing elements, prose flows through them. Any other tag ends a run.
+const INLINE: &[&str] = &[
+  "a", "abbr", "b", "bdi", "bdo", "cite", "data", "del", "dfn", "em", "font", "i", "img", "ins",
+  "kbd", "label", "mark", "q", "s", "samp", "small", "span", "strong", "sub", "sup", "time", "u",
+  "var", "wbr",
+];
+
+/// Elements whose content is kept byte for byte.
+const SKIP: &[&str] = &["script", "style", "pre"];
+
+/// Elements holding raw text, no tag inside until their end tag.
+const RAW: &[&str] = &["script", "style"];
+
+/// Char of an entity, the `;` included, like `&amp;` or `&#x4e2d;`.
+pub(crate) fn entity(s: &str) -> Option<(char, usize)> {
+  let end = s.bytes().take(12).position(|b| b == b';')?;
+  let name = &s[1..end];
+  let c = if let Some(hex) = name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+    char::from_u32(u32::from_str_radix(hex, 16).ok()?)?
+  } else if let Some(dec) = name.strip_prefix('#') {
+    char::from_u32(dec.parse().ok()?)?
+  } else {
+    match name {
+      "amp" => '&',
+      "lt" => '<',
+      "gt" => '>',
+      "quot" => '"',
+      "apos" => '\'',
+      "nbsp" => '\u{a0}',
+      "ensp" => '\u{2002}',
+      "emsp" => '\u{2003}',
+      "thinsp" => '\u{2009}',
+      "mdash" => '—',
+      "ndash" => '–',
+      "hellip" => '…',
+      "middot" => '·',
+      "lsquo" => '‘',
+      "rsquo" => '’',
+      "ldquo" => '“',
+      "rdquo" => '”',
+      "copy" => '©',
+      "reg" => '®',
+      _ => return None,
+    }
+  };
+  Some((c, end + 1))
+}
+
+/// Text at `range` with its entities, into `project`.
+fn text(project: &mut Project, src: &str, range: Range<usize>) {
+  let mut start = range.start;
+  let mut i = start;
+  while i < range.end {
+    if src.as_bytes()[i] == b'&'
+      && let Some((c, len)) = entity(&src[i..range.end])
+    {
+      project.text(src, start..i);
+      project.escaped(c, i..i + len);
+      i += len;
+      start = i;
+    } else {
+      i += 1;
+    }
+  }
+  project.text(src, start..range.end);
+}
+
+/// Start or end tag at the start of `s`.
+pub(crate) struct Tag {
+  pub name: String,
+  pub end: bool,
+  /// Byte length of the tag, `<` to `>`
+  pub len: usize,
+  /// Name and value range of the attributes, relative to `s`
+  pub attrs: Vec<(String, Range<usize>)>,
+}
+
+fn is_name(b: u8) -> bool {
+  !b.is_ascii_whitespace() && !matches!(b, b'>' | b'/' | b'=')
+}
+
+impl Tag {
+  pub fn parse(s: &str) -> Option<Self> {
+    let b = s.as_bytes();
+    let end = b.get(1) == Some(&b'/');
+    let mut i = 1 + end as usize;
+    if !b.get(i)?.is_ascii_alphabetic() {
+      return None;
+    }
+    let start = i;
+    while i < b.len() && is_name(b[i]) {
+      i += 1;
+    }
+    let name = s[start..i].to_ascii_lowercase();
+    let mut attrs = Vec::new();
+    loop {
+      while i < b.len() && (b[i].is_ascii_whitespace() || b[i] == b'/') {
+        i += 1;
+      }
+      match b.get(i) {
+        None => break,
+        Some(b'>') => {
+          i += 1;
+          break;
+        }
+        _ => {}
+      }
+      let start = i;
+      // a stray `=` starts a name
+      i += 1;
+      while i < b.len() && is_name(b[i]) {
+        i += 1;
+      }
+      let attr = s[start..i].to_ascii_lowercase();
+      let mut j = i;
+      while j < b.len() && b[j].is_ascii_whitespace() {
+        j += 1;
+      }
+      if b.get(j) != Some(&b'=') {
+        continue;
+      }
+      i = j + 1;
+      while i < b.len() && b[i].is_ascii_whitespace() {
+        i += 1;
+      }
+      let value = match b.get(i) {
+        Some(&q @ (b'"' | b'\'')) => {
+          let end = s[i + 1..].find(q as char).map_or(b.len(), |e| i + 1 + e);
+          let value = i + 1..end;
+          i = (end + 1).min(b.len());
+          value
+        }
+        _ => {
+          let start = i;
+          while i < b.len() && !b[i].is_ascii_whitespace() && b[i] != b'>' {
+            i += 1;
+          }
+          start..i
+        }
+      };
+      attrs.push((attr, value));
+    }
+    Some(Self {
+      name,
+      end,
+      len: i,
+      attrs,
+    })
+  }
+}
+
+/// Offset of the `</name` closing the element whose content starts at `from`, the end of `src`
+/// when missing.
+pub(crate) fn close(src: &str, from: usize, name: &str) -> usize {
+  let b = src.as_bytes();
+  let mut at = from;
+  while let Some(i) = src[at..].find("</") {
+    let i = at + i;
+    let end = i + 2 + name.len();
+    if b
+      .get(i + 2..end)
+      .is_some_and(|n| n.eq_ignore_ascii_case(name.as_bytes()))
+      && b.get(end).is_none_or(|&c| !is_name(c))
+    {
+      return i;
+    }
+    at = i + 2;
+  }
+  src.len()
+}
+
+pub(crate) fn html_edits(src: &str, opts: &Options) -> Vec<Edit> {
+  let mut project = Project::default();
+  // attribute values, each one a run of its own
+  let mut attrs = Project::default();
+  // depth of skipped elements
+  let mut skip = 0usize;
+  let mut i = 0;
+  let mut start = 0;
+
+  while let Some(lt) = src[i..].find('<') {
+    let lt = i + lt;
+    let rest = &src[lt..];
+    let (len, tag) = if rest.starts_with("<!--") {
+      (rest.find("-->").map_or(rest.len(), |e| e + 3), None)
+    } else if rest.starts_with("<!") || rest.starts_with("<?") {
+      (rest.find('>').map_or(rest.len(), |e| e + 1), None)
+    } else if let Some(tag) = Tag::parse(rest) {
+      (tag.len, Some(tag))
+    } else {
+      i = lt + 1;
+      continue;
+    };
+    if skip == 0 {
+      text(&mut project, src, start..lt);
+    }
+    i = lt + len;
+    start = i;
+
+    let Some(tag) = tag else {
+      project.brk();
+      continue;
+    };
+    let name = tag.name.as_str();
+    if skip == 0 && !tag.end {
+      for (attr, value) in &tag.attrs {
+        if opts
+          .html_attributes
+          .iter()
+          .any(|a| a.eq_ignore_ascii_case(attr))
+        {
+          text(&mut attrs, src, lt + value.start..lt + value.end);
+          attrs.brk();
+        }
+      }
+    }
+    if SKIP.contains(&name) {
+      project.brk();
+      if tag.end {
+        skip = skip.saturating_sub(1);
+      } else {
+        skip += 1;
+        if RAW.contains(&name) {
+          i = close(src, i, name);
+          start = i;
+        }
+      }
+    } else if skip > 0 {
+      continue;
+    } else if name == "code" {
+      if !tag.end {
+        // a code span, spaced like a word
+        let end = close(src, i, name);
+        i = Tag::parse(&src[end..]).map_or(end, |t| end + t.len);
+        start = i;
+        project.atom(lt..i);
+      }
+    } else if INLINE.contains(&name) {
+      if !tag.end {
+        project.anchor(lt);
+      }
+    } else {
+      project.brk();
+    }
+  }
+  if skip == 0 {
+    text(&mut project, src, start..src.len());
+  }
+
+  let mut r = project.edits(src, opts);
+  r.extend(attrs.edits(src, opts));
+  r.sort_by_key(|e| (e.range.start, e.range.end));
+  r
+}
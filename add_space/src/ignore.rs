@@ -0,0 +1,109 @@
+use std::ops::Range;
+
+use crate::Edit;
+
+/// Common prefix of the directives, documents without it are not scanned.
+const PREFIX: &str = "add_space-ignore";
+
+/// Bytes at the start of a line enough to tell its directive, streams keep no more of a line.
+pub(crate) const HEAD: usize = 256;
+
+/// Comment openers a directive may follow, `<!--` for Markdown, HTML and XML.
+const OPENERS: &[&str] = &["<!--", "//", "/*", "#", "--", ";", "%"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Directive {
+  /// `add_space-ignore-start`, lines are kept up to and including the end directive
+  Start,
+  /// `add_space-ignore-end`
+  End,
+  /// `add_space-ignore-next-line`
+  NextLine,
+}
+
+/// Directive a comment alone on `line` holds, like `<!-- add_space-ignore-start -->` or
+/// `# add_space-ignore-next-line: sample of bad typography`.
+fn directive(line: &str) -> Option<Directive> {
+  let s = line.trim();
+  let opener = OPENERS.iter().find(|o| s.starts_with(**o))?;
+  let s = s[opener.len()..]
+    .trim_start_matches(['/', '*', '#', '-', ';', '%', '!'])
+    .trim_start();
+  let s = s.strip_suffix("-->").or(s.strip_suffix("*/")).unwrap_or(s);
+  let rest = s.strip_prefix(PREFIX)?;
+  // a reason may follow
+  let name = rest.split(|c: char| c.is_whitespace() || c == ':').next();
+  match name? {
+    "-start" => Some(Directive::Start),
+    "-end" => Some(Directive::End),
+    "-next-line" => Some(Directive::NextLine),
+    _ => None,
+  }
+}
+
+/// Lines to keep as is, fed one line at a time.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Ignore {
+  region: bool,
+  next: bool,
+}
+
+impl Ignore {
+  /// Whether the line about to come is kept as is.
+  pub(crate) fn skips(&self) -> bool {
+    self.region || self.next
+  }
+
+  /// Moves past `line`, which may hold a directive.
+  pub(crate) fn line(&mut self, line: &str) {
+    self.next = false;
+    if !line.contains(PREFIX) {
+      return;
+    }
+    match directive(line) {
+      Some(Directive::Start) => self.region = true,
+      Some(Directive::End) => self.region = false,
+      Some(Directive::NextLine) => self.next = !self.region,
+      None => {}
+    }
+  }
+}
+
+/// Whether `txt` may hold a directive.
+pub(crate) fn has_directive(txt: &str) -> bool {
+  txt.contains(PREFIX)
+}
+
+/// Byte ranges of the lines directives keep as is.
+fn ignored(txt: &str) -> Vec<Range<usize>> {
+  let mut r: Vec<Range<usize>> = Vec::new();
+  let mut ignore = Ignore::default();
+  let mut start = 0;
+  for line in txt.split_inclusive('\n') {
+    if ignore.skips() {
+      match r.last_mut() {
+        Some(last) if last.end == start => last.end = start + line.len(),
+        _ => r.push(start..start + line.len()),
+      }
+    }
+    ignore.line(line);
+    start += line.len();
+  }
+  r
+}
+
+/// Drops the edits inside lines kept by directives.
+pub(crate) fn retain(txt: &str, edits: &mut Vec<Edit>) {
+  if !has_directive(txt) {
+    return;
+  }
+  let ignored = ignored(txt);
+  if ignored.is_empty() {
+    return;
+  }
+  edits.retain(|e| {
+    !ignored
+      .iter()
+      .any(|r| r.start <= e.range.start && e.range.start < r.end)
+  });
+}
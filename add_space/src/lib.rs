@@ -1,21 +1,55 @@
-use unicode_script::{Script, UnicodeScript};
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+pub use unicode_script::Script;
+use unicode_script::UnicodeScript;
+
+mod code;
+#[cfg(feature = "conf")]
+mod conf;
+mod data;
+mod edit;
+mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod html;
+mod ignore;
+mod md;
+mod mode;
+mod options;
+mod placeholder;
+mod project;
+mod protect;
+mod remove;
+mod stream;
+mod subtitle;
+mod translation;
+mod unit;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod width;
+
+pub use code::Lang;
+#[cfg(feature = "conf")]
+pub use conf::{CONF_FILE, Conf};
+pub use data::Data;
+pub use edit::{Edit, Rule, apply_edits};
+pub use error::{Error, Result};
+pub use md::{add_space_md, add_space_md_with, is_md};
+pub use mode::Mode;
+pub use options::{Options, PUNCTUATION, SCRIPT_RULES, SCRIPTS, ScriptRule};
+pub use placeholder::Placeholder;
+pub use protect::Protect;
+pub use regex::Regex;
+pub use stream::{AddSpaceChars, AddSpaceWriter, add_space_chars, add_space_io};
+pub use subtitle::Subtitle;
+pub use translation::Translation;
+pub use unit::{ATTACHED_UNITS, UNITS};
 
 pub fn state(c: char) -> State {
   if c.is_whitespace() {
     return State::Space;
   }
-  if matches!(
-    c.script(),
-    Script::Han
-      | Script::Hiragana
-      | Script::Katakana
-      | Script::Thai
-      | Script::Lao
-      | Script::Khmer
-      | Script::Myanmar
-      | Script::Tibetan
-  ) || ('０'..='９').contains(&c)
-  {
+  if SCRIPTS.contains(&c.script()) || ('０'..='９').contains(&c) {
     return State::Char;
   }
 
@@ -23,12 +57,14 @@ pub fn state(c: char) -> State {
     return State::Letter;
   }
 
-  if r##"!"#%\'*+,-.:<=>?@^·—‘’“”…、。「」『』！，：？；（）"##.contains(c)
-    || (c.len_utf8() > 1 && unic_emoji_char::is_emoji(c))
-  {
+  if PUNCTUATION.contains(c) || (c.len_utf8() > 1 && unic_emoji_char::is_emoji(c)) {
     return State::Punctuation;
   }
 
+  if c.len_utf8() > 1 && options::script_of(&SCRIPTS, c).is_some() {
+    return State::Char;
+  }
+
   State::Letter
 }
 
@@ -38,8 +74,9 @@ fn push_stack(c: char, stack: &mut Vec<char>) {
   }
 }
 
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
 pub enum State {
+  #[default]
   Space,
   Char,
   Letter,
@@ -50,61 +87,123 @@ pub fn state_is_letter_or_punctuation(s: State) -> bool {
   matches!(s, State::Letter | State::Punctuation)
 }
 
-pub fn add_space(txt: impl AsRef<str>) -> String {
-  let txt = txt.as_ref();
-  let mut r = String::new();
-  let mut iter = txt.chars().peekable();
-
-  if let Some(c) = iter.next() {
-    r.push(c);
-    let mut is_escape = c == '\\';
-    let mut pre_state = state(c);
-    let mut pre_pre_state = State::Space;
-    let mut pre_c = c;
-    let mut stack = Vec::new();
-
-    push_stack(c, &mut stack);
-
-    while let Some(c) = iter.next() {
-      if is_escape {
-        is_escape = false;
-        r.push(c);
-        continue;
+/// Char-by-char spacing state machine behind [`add_space_with`].
+#[derive(Debug)]
+pub struct Spacer<'a> {
+  opts: &'a Options,
+  started: bool,
+  is_escape: bool,
+  pre_state: State,
+  pre_pre_state: State,
+  pre_c: char,
+  pre_pre_c: char,
+  stack: Vec<char>,
+}
+
+impl<'a> Spacer<'a> {
+  pub fn new(opts: &'a Options) -> Self {
+    Self {
+      opts,
+      started: false,
+      is_escape: false,
+      pre_state: State::Space,
+      pre_pre_state: State::Space,
+      pre_c: '\0',
+      pre_pre_c: '\0',
+      stack: Vec::new(),
+    }
+  }
+
+  /// Feeds `c` (with a one char lookahead `next`), returns whether a space must be inserted before it.
+  pub fn push(&mut self, c: char, next: Option<char>) -> bool {
+    self.rule(c, next).is_some()
+  }
+
+  /// Like [`Spacer::push`], returns the rule asking for the space.
+  pub fn rule(&mut self, c: char, next: Option<char>) -> Option<Rule> {
+    if !self.started {
+      // a leading BOM is invisible to the rules
+      if c == '\u{feff}' {
+        return None;
       }
-      let s = state(c);
-      push_stack(c, &mut stack);
-      match s {
-        State::Char => {
-          if pre_state == State::Letter
-            && !"[({".contains(pre_c)
-            && state_is_letter_or_punctuation(pre_pre_state)
-          {
-            r.push(' ');
-          }
-          r.push(c);
-        }
-        State::Letter => {
-          is_escape = c == '\\';
-          if let Some(stack_last) = stack.last() {
-            if matches!((stack_last, c), ('[', ']') | ('(', ')') | ('{', '}')) {
-              stack.pop();
-            }
-          } else if ((!is_escape && pre_state == State::Char)
-            || (",?!…".contains(pre_c))
-            || (pre_c == '.' && c.is_uppercase()))
-            && let Some(c) = iter.peek()
-            && state_is_letter_or_punctuation(state(*c))
-          {
-            r.push(' ');
+      self.started = true;
+      self.is_escape = c == '\\';
+      self.pre_state = self.opts.state(c);
+      self.pre_c = c;
+      push_stack(c, &mut self.stack);
+      return None;
+    }
+
+    if self.is_escape {
+      self.is_escape = false;
+      return None;
+    }
+
+    let pre_state = self.pre_state;
+    let pre_pre_state = self.pre_pre_state;
+    let pre_c = self.pre_c;
+    // `1,024` is one number
+    let in_number = pre_c == ',' && self.pre_pre_c.is_ascii_digit() && c.is_ascii_digit();
+    let s = self.opts.state(c);
+    push_stack(c, &mut self.stack);
+    let mut rule = None;
+    match s {
+      State::Char
+        if pre_state == State::Letter
+          && !"[({".contains(pre_c)
+          && state_is_letter_or_punctuation(pre_pre_state)
+          && self.opts.rule_of(c).after_word =>
+      {
+        rule = Some(Rule::WordCjk);
+      }
+      State::Letter => {
+        self.is_escape = c == '\\';
+        if let Some(stack_last) = self.stack.last() {
+          if matches!((stack_last, c), ('[', ']') | ('(', ')') | ('{', '}')) {
+            self.stack.pop();
           }
-          r.push(c);
+        } else if next.is_some_and(|c| state_is_letter_or_punctuation(self.opts.state(c))) {
+          rule = if !self.is_escape && pre_state == State::Char {
+            self
+              .opts
+              .rule_of(pre_c)
+              .before_word
+              .then_some(Rule::CjkWord)
+          } else if self.opts.space_after_punctuation && ",?!…".contains(pre_c) && !in_number {
+            Some(Rule::Punctuation)
+          } else if self.opts.space_after_period && pre_c == '.' && c.is_uppercase() {
+            Some(Rule::Period)
+          } else {
+            None
+          };
         }
-        _ => r.push(c),
       }
-      pre_pre_state = pre_state;
-      pre_state = s;
-      pre_c = c;
+      _ => {}
     }
+    self.pre_pre_state = pre_state;
+    self.pre_state = s;
+    self.pre_pre_c = pre_c;
+    self.pre_c = c;
+    rule
   }
-  r
+}
+
+pub fn add_space(txt: impl AsRef<str>) -> String {
+  add_space_with(txt, &Options::default())
+}
+
+/// Spaces [`Mode::Text`] inserts into `txt`, as edits.
+pub fn edits(txt: impl AsRef<str>, opts: &Options) -> Vec<Edit> {
+  Mode::Text.edits(txt.as_ref(), opts)
+}
+
+/// Removes spurious spaces from CJK text, like those left by OCR or machine translation, and adds
+/// the missing ones: `中 文 内 容 ， 和English` -> `中文内容，和 English`.
+pub fn normalize(txt: impl AsRef<str>) -> String {
+  add_space_with(txt, &Options::default().remove_space(true))
+}
+
+pub fn add_space_with(txt: impl AsRef<str>, opts: &Options) -> String {
+  // line by line like the CLI and streams
+  Mode::Text.format(txt.as_ref(), opts)
 }
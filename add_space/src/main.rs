@@ -1,51 +1,363 @@
 use std::{
+  collections::HashMap,
+  fmt::Write as _,
   fs,
-  io::{self, Read},
+  io::{self, IsTerminal, Read},
+  path::{Path, PathBuf},
+  process::ExitCode,
+  sync::{Arc, Mutex},
 };
 
-use add_space::add_space;
-use clap::Parser;
+use add_space::{Conf, Mode, add_space_io};
+use aok::{Context, Result, anyhow};
+use clap::{Parser, ValueEnum};
+use globset::{Glob, GlobMatcher};
+use ignore::WalkBuilder;
+use rayon::prelude::*;
+use similar::{ChangeTag, TextDiff};
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Color {
+  Auto,
+  Always,
+  Never,
+}
+
+impl Color {
+  fn on(self) -> bool {
+    match self {
+      Color::Auto => io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+      Color::Always => true,
+      Color::Never => false,
+    }
+  }
+}
 
 #[derive(Parser)]
 #[command(name = "add_space", author, version, about, long_about = None)]
 struct Cli {
-  /// The path to the file to process, or stdin if not provided
-  path: Option<String>,
+  /// Files, directories (walked recursively, honoring .gitignore) or globs, stdin if not provided
+  paths: Vec<String>,
 
   /// Write the output back to the file
   #[arg(short, long)]
   write: bool,
+
+  /// Print a diff and exit with status 1 if the input would change, like `rustfmt --check`
+  #[arg(long, conflicts_with = "write")]
+  check: bool,
+
+  /// Colorize the --check diff
+  #[arg(long, value_enum, default_value = "auto")]
+  color: Color,
+
+  /// Treat the input as Markdown, only prose is spaced (default for .md / .markdown / .mdt files)
+  #[arg(short, long)]
+  markdown: bool,
+
+  /// Treat the input as HTML, only text nodes are spaced (default for .html / .htm / .xhtml files)
+  #[arg(long, conflicts_with = "markdown")]
+  html: bool,
+
+  /// Configuration file, by default the nearest .add_space.toml or Cargo.toml with an add_space table
+  /// walking up from each file (or the current directory)
+  #[arg(short, long, conflicts_with = "no_config")]
+  config: Option<PathBuf>,
+
+  /// Ignore configuration files
+  #[arg(long)]
+  no_config: bool,
+
+  /// File extensions picked up when walking directories [default: every extension with a mode,
+  /// built in or configured, and the files matching `include`]
+  #[arg(short, long, value_delimiter = ',')]
+  ext: Vec<String>,
+
+  /// Number of parallel workers, 0 for one per CPU
+  #[arg(short, long, default_value_t = 0)]
+  jobs: usize,
 }
 
-fn main() -> io::Result<()> {
-  let cli = Cli::parse();
+fn diff(name: &str, old: &str, new: &str, color: bool) -> String {
+  let paint = |code: &str, txt: &str| {
+    if color && !code.is_empty() {
+      format!("\x1b[{code}m{txt}\x1b[0m")
+    } else {
+      txt.to_owned()
+    }
+  };
+  let mut r = String::new();
+  let _ = writeln!(r, "{}", paint("1", &format!("--- {name}\n+++ {name}")));
+  let diff = TextDiff::from_lines(old, new);
+  for hunk in diff.unified_diff().context_radius(3).iter_hunks() {
+    let _ = writeln!(r, "{}", paint("36", &hunk.header().to_string()));
+    for change in hunk.iter_changes() {
+      let (sign, code) = match change.tag() {
+        ChangeTag::Delete => ("-", "31"),
+        ChangeTag::Insert => ("+", "32"),
+        ChangeTag::Equal => (" ", ""),
+      };
+      let line = change.to_string_lossy();
+      let _ = writeln!(
+        r,
+        "{}",
+        paint(
+          code,
+          &format!("{sign}{}", line.trim_end_matches(['\n', '\r']))
+        )
+      );
+      if change.missing_newline() {
+        r.push_str("\\ No newline at end of file\n");
+      }
+    }
+  }
+  r
+}
+
+fn is_glob(path: &str) -> bool {
+  path.contains(['*', '?', '[', '{'])
+}
+
+/// Expands the command line paths into files, walking directories and globs.
+fn files(paths: &[String], ext: &[String], confs: &Confs) -> Result<Vec<PathBuf>> {
+  let mut r = Vec::new();
+  for path in paths {
+    let (root, glob): (PathBuf, Option<GlobMatcher>) = if is_glob(path) && !Path::new(path).exists()
+    {
+      let root = Path::new(path)
+        .components()
+        .take_while(|c| !is_glob(&c.as_os_str().to_string_lossy()))
+        .collect::<PathBuf>();
+      // walked files have their leading `./` stripped, so does the glob
+      let glob = Glob::new(path.trim_start_matches("./"))?;
+      (root, Some(glob.compile_matcher()))
+    } else {
+      (path.into(), None)
+    };
+
+    if root.is_file() {
+      r.push(root);
+      continue;
+    }
+
+    let walk_root = if root.as_os_str().is_empty() {
+      Path::new(".")
+    } else {
+      root.as_path()
+    };
+    if !walk_root.exists() {
+      return Err(anyhow!("{path}: no such file or directory"));
+    }
+
+    for entry in WalkBuilder::new(walk_root).build() {
+      let entry = entry?;
+      if !entry.file_type().is_some_and(|t| t.is_file()) {
+        continue;
+      }
+      let file = entry.path();
+      let file = file.strip_prefix(".").unwrap_or(file);
+      let pick = match &glob {
+        Some(glob) => glob.is_match(file),
+        None if ext.is_empty() => confs.get(file)?.is_walked(file),
+        None => file
+          .extension()
+          .and_then(|e| e.to_str())
+          .is_some_and(|e| ext.iter().any(|x| x.eq_ignore_ascii_case(e))),
+      };
+      if pick {
+        r.push(file.to_path_buf());
+      }
+    }
+  }
+  r.sort();
+  r.dedup();
+  Ok(r)
+}
+
+/// Configuration per directory, so thousands of files do not each walk up the tree.
+struct Confs {
+  fixed: Option<Arc<Conf>>,
+  by_dir: Mutex<HashMap<PathBuf, Arc<Conf>>>,
+}
 
-  let (content, from_stdin) = if let Some(path) = &cli.path {
-    (fs::read_to_string(path)?, false)
+impl Confs {
+  fn new(cli: &Cli) -> Result<Self> {
+    let fixed = if cli.no_config {
+      Some(Conf::default())
+    } else if let Some(config) = &cli.config {
+      Some(
+        Conf::load(config)
+          .with_context(|| config.display().to_string())?
+          .with_context(|| format!("{}: no add_space table", config.display()))?,
+      )
+    } else {
+      None
+    };
+    Ok(Self {
+      fixed: fixed.map(Arc::new),
+      by_dir: Mutex::default(),
+    })
+  }
+
+  /// `path` is a file, or the directory a stdin document belongs to.
+  fn get(&self, path: &Path) -> Result<Arc<Conf>> {
+    if let Some(conf) = &self.fixed {
+      return Ok(conf.clone());
+    }
+    let dir = if path.is_dir() {
+      path
+    } else {
+      path.parent().unwrap_or(Path::new("."))
+    };
+    if let Some(conf) = self.by_dir.lock().unwrap().get(dir) {
+      return Ok(conf.clone());
+    }
+    let conf = Arc::new(Conf::find(path)?.unwrap_or_default());
+    self
+      .by_dir
+      .lock()
+      .unwrap()
+      .insert(dir.to_path_buf(), conf.clone());
+    Ok(conf)
+  }
+}
+
+enum Outcome {
+  Changed(String),
+  Unchanged,
+  Skipped,
+}
+
+fn run(cli: &Cli, confs: &Confs, path: &Path) -> Result<Outcome> {
+  let conf = confs.get(path)?;
+  if !conf.is_included(path) {
+    return Ok(Outcome::Skipped);
+  }
+  let content = fs::read_to_string(path)?;
+  let mode = if cli.markdown {
+    Mode::Markdown
+  } else if cli.html {
+    Mode::Html
   } else {
-    let mut buffer = String::new();
-    io::stdin().read_to_string(&mut buffer)?;
-    (buffer, true)
+    conf.mode(path)
   };
+  let new_content = mode.format(&content, &conf.options);
+  let changed = new_content != content;
+
+  if cli.write && changed {
+    fs::write(path, &new_content)?;
+  }
 
-  let new_content = content
-    .lines()
-    .map(add_space)
-    .collect::<Vec<_>>()
-    .join("\n");
+  Ok(if !changed {
+    Outcome::Unchanged
+  } else if cli.check {
+    Outcome::Changed(diff(
+      &path.display().to_string(),
+      &content,
+      &new_content,
+      cli.color.on(),
+    ))
+  } else {
+    Outcome::Changed(new_content)
+  })
+}
 
+fn stdin(cli: &Cli, confs: &Confs) -> Result<ExitCode> {
   if cli.write {
-    if from_stdin {
-      eprintln!("Error: cannot use --write with stdin.");
-      std::process::exit(1);
+    eprintln!("Error: cannot use --write with stdin.");
+    return Ok(ExitCode::FAILURE);
+  }
+  let conf = confs.get(Path::new("."))?;
+  if !cli.markdown && !cli.html && !cli.check {
+    add_space_io(io::stdin().lock(), io::stdout().lock(), &conf.options)?;
+    return Ok(ExitCode::SUCCESS);
+  }
+  let mut content = String::new();
+  io::stdin().read_to_string(&mut content)?;
+  let mode = if cli.markdown {
+    Mode::Markdown
+  } else if cli.html {
+    Mode::Html
+  } else {
+    Mode::Text
+  };
+  let new_content = mode.format(&content, &conf.options);
+
+  if cli.check {
+    if new_content == content {
+      return Ok(ExitCode::SUCCESS);
     }
-    if let Some(path) = &cli.path {
-      fs::write(path, new_content)?;
-      println!("File {} has been updated.", path);
+    print!(
+      "{}",
+      diff("<stdin>", &content, &new_content, cli.color.on())
+    );
+    return Ok(ExitCode::FAILURE);
+  }
+  print!("{}", new_content);
+  Ok(ExitCode::SUCCESS)
+}
+
+fn main() -> Result<ExitCode> {
+  let cli = Cli::parse();
+  let confs = Confs::new(&cli)?;
+
+  if cli.paths.is_empty() {
+    return stdin(&cli, &confs);
+  }
+
+  let files = files(&cli.paths, &cli.ext, &confs)?;
+
+  // a single file without --write / --check is printed
+  if !cli.write && !cli.check {
+    if let [file] = &files[..]
+      && cli.paths.len() == 1
+      && Path::new(&cli.paths[0]).is_file()
+    {
+      match run(&cli, &confs, file)? {
+        Outcome::Changed(txt) => print!("{}", txt),
+        _ => print!("{}", fs::read_to_string(file)?),
+      }
+      return Ok(ExitCode::SUCCESS);
+    }
+    eprintln!("Error: use --write or --check with several files or directories.");
+    return Ok(ExitCode::FAILURE);
+  }
+
+  let pool = rayon::ThreadPoolBuilder::new()
+    .num_threads(cli.jobs)
+    .build()?;
+  let outcomes: Vec<_> = pool.install(|| {
+    files
+      .par_iter()
+      .map(|file| run(&cli, &confs, file))
+      .collect()
+  });
+
+  let (mut changed, mut unchanged, mut failed) = (0, 0, 0);
+  for (file, outcome) in files.iter().zip(outcomes) {
+    match outcome {
+      Ok(Outcome::Changed(txt)) => {
+        changed += 1;
+        if cli.check {
+          print!("{}", txt);
+        } else {
+          println!("File {} has been updated.", file.display());
+        }
+      }
+      Ok(Outcome::Unchanged) => unchanged += 1,
+      Ok(Outcome::Skipped) => {}
+      Err(err) => {
+        failed += 1;
+        eprintln!("Error: {}: {err}", file.display());
+      }
     }
-  } else {
-    print!("{}", new_content);
   }
 
-  Ok(())
+  eprintln!("{changed} changed, {unchanged} unchanged, {failed} failed");
+
+  Ok(if failed > 0 || (cli.check && changed > 0) {
+    ExitCode::FAILURE
+  } else {
+    ExitCode::SUCCESS
+  })
 }
@@ -0,0 +1,198 @@
+use std::{ops::Range, path::Path};
+
+use pulldown_cmark::{Event, LinkType, Options as CmarkOptions, Parser, Tag, TagEnd};
+
+use crate::{Edit, Mode, Options, project::Project};
+
+pub fn is_md(path: impl AsRef<Path>) -> bool {
+  path
+    .as_ref()
+    .extension()
+    .and_then(|ext| ext.to_str())
+    .is_some_and(|ext| matches!(ext.to_ascii_lowercase().as_str(), "md" | "markdown" | "mdt"))
+}
+
+fn is_inline(tag: &Tag) -> bool {
+  matches!(
+    tag,
+    Tag::Emphasis
+      | Tag::Strong
+      | Tag::Strikethrough
+      | Tag::Superscript
+      | Tag::Subscript
+      | Tag::Link { .. }
+      | Tag::Image { .. }
+  )
+}
+
+fn is_inline_end(tag: &TagEnd) -> bool {
+  matches!(
+    tag,
+    TagEnd::Emphasis
+      | TagEnd::Strong
+      | TagEnd::Strikethrough
+      | TagEnd::Superscript
+      | TagEnd::Subscript
+      | TagEnd::Link
+      | TagEnd::Image
+  )
+}
+
+/// Like [`crate::add_space`], but only spaces the prose of a Markdown document: code blocks, code
+/// spans, link targets, front matter and raw HTML are kept byte for byte.
+pub fn add_space_md(txt: impl AsRef<str>) -> String {
+  add_space_md_with(txt, &Options::default())
+}
+
+pub fn add_space_md_with(txt: impl AsRef<str>, opts: &Options) -> String {
+  let txt = txt.as_ref();
+  Mode::Markdown.format(txt, opts)
+}
+
+/// Offsets of the `*`, `_` or `~` in the block at `range` of `txt` that are left as text in `block`
+/// and occur more than once: a space next to any of them may pair them up on the next run.
+fn delimiters(block: &mut Vec<&str>, txt: &str, range: Range<usize>, out: &mut Vec<usize>) {
+  block.sort_unstable();
+  block.dedup();
+  let src = &txt[range.clone()];
+  for m in block.drain(..) {
+    if src.matches(m).count() > 1 {
+      out.extend(src.match_indices(m).map(|(i, _)| range.start + i));
+    }
+  }
+}
+
+pub(crate) fn md_edits(txt: &str, opts: &Options) -> Vec<Edit> {
+  let mut project = Project::default();
+  // depth of code blocks, front matter, html blocks and autolinks
+  let mut skip = 0usize;
+  // `*`, `_` and `~` left as text in the current block
+  let mut block = Vec::new();
+  let mut block_range = 0..0;
+  let mut delimiter = Vec::new();
+
+  let options = CmarkOptions::ENABLE_TABLES
+    | CmarkOptions::ENABLE_FOOTNOTES
+    | CmarkOptions::ENABLE_STRIKETHROUGH
+    | CmarkOptions::ENABLE_TASKLISTS
+    | CmarkOptions::ENABLE_YAML_STYLE_METADATA_BLOCKS
+    | CmarkOptions::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS;
+
+  for (event, range) in Parser::new_ext(txt, options).into_offset_iter() {
+    match event {
+      Event::Start(tag) => {
+        if let Tag::Link {
+          link_type: LinkType::Autolink | LinkType::Email,
+          ..
+        } = tag
+        {
+          skip += 1;
+          if skip == 1 {
+            project.atom(range);
+          }
+        } else if is_inline(&tag) {
+          project.anchor(range.start);
+        } else {
+          if matches!(
+            tag,
+            Tag::CodeBlock(_) | Tag::HtmlBlock | Tag::MetadataBlock(_)
+          ) {
+            skip += 1;
+          }
+          project.brk();
+          delimiters(&mut block, txt, block_range.clone(), &mut delimiter);
+          block_range = range;
+        }
+      }
+      Event::End(tag) => {
+        if matches!(
+          tag,
+          TagEnd::CodeBlock | TagEnd::HtmlBlock | TagEnd::MetadataBlock(_)
+        ) {
+          skip = skip.saturating_sub(1);
+          project.brk();
+          delimiters(&mut block, txt, block_range.clone(), &mut delimiter);
+        } else if !is_inline_end(&tag) {
+          project.brk();
+          delimiters(&mut block, txt, block_range.clone(), &mut delimiter);
+        } else if tag == TagEnd::Link && skip > 0 {
+          skip -= 1;
+        }
+      }
+      Event::Text(_) | Event::SoftBreak => {
+        if skip == 0 {
+          block.extend(txt[range.clone()].matches(['*', '_', '~']));
+          project.text(txt, range);
+        }
+      }
+      Event::Code(_) => {
+        if skip == 0 {
+          project.atom(range);
+        }
+      }
+      _ => project.brk(),
+    }
+  }
+  delimiters(&mut block, txt, block_range.clone(), &mut delimiter);
+
+  let mut r = project.edits(txt, opts);
+  delimiter.sort_unstable();
+  let is_delimiter = |i| delimiter.binary_search(&i).is_ok();
+  r.retain(|e| {
+    // anything right after `\` would undo the escape
+    let escaped = txt[..e.range.start].ends_with('\\');
+    // a space next to a delimiter may open or close emphasis
+    let delimited =
+      e.range.start.checked_sub(1).is_some_and(is_delimiter) || is_delimiter(e.range.end);
+    !escaped
+      && !delimited
+      && (e.text.trim().is_empty() || !is_markup(txt, e))
+      && (!e.text.is_empty() || !unflanks(txt, e))
+  });
+  r
+}
+
+/// Whether deleting the spaces at `e` would stop a `*`, `_` or `~` run next to them from closing
+/// or opening emphasis: `**「中文」** 内容` only closes with the space, `_` never does inside a
+/// word.
+fn unflanks(txt: &str, e: &Edit) -> bool {
+  // punctuation, whitespace and the ends of the text
+  let punct = |c: Option<char>| c.is_none_or(|c| !c.is_alphanumeric());
+  let pre = &txt[..e.range.start];
+  let next = &txt[e.range.end..];
+  let breaks = |delimiter: Option<char>, inner: Option<char>, outer: Option<char>| {
+    (delimiter == Some('_') || punct(inner)) && !punct(outer)
+  };
+  let before = pre.trim_end_matches(['*', '_', '~']);
+  let after = next.trim_start_matches(['*', '_', '~']);
+  (before.len() < pre.len()
+    && breaks(
+      pre.chars().last(),
+      before.chars().last(),
+      next.chars().next(),
+    ))
+    || (after.len() < next.len()
+      && breaks(
+        next.chars().next(),
+        after.chars().next(),
+        pre.chars().last(),
+      ))
+}
+
+/// Whether a width replacement would change markup: `\（` or `\(` lose or gain their escape, `]（`
+/// turns into a link, `！[` into an image and `1。` into a list item.
+fn is_markup(txt: &str, e: &Edit) -> bool {
+  // a space added in between does not count, the next run would see it
+  let pre = txt[..e.range.start].trim_end_matches(' ');
+  let next = txt[e.range.end..].trim_start_matches(' ');
+  pre.ends_with('\\')
+    || match e.text.as_str() {
+      "(" | ":" => pre.ends_with(']'),
+      "!" => next.starts_with('['),
+      "." | ")" => pre
+        .rsplit('\n')
+        .next()
+        .is_some_and(|line| line.trim_start().bytes().all(|b| b.is_ascii_digit())),
+      _ => false,
+    }
+}
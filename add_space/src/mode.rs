@@ -0,0 +1,124 @@
+use std::path::Path;
+
+use crate::{
+  Edit, Lang, Options, apply_edits,
+  code::code_edits,
+  data::{Data, data_edits},
+  edit::set_char_range,
+  html::{html_edits, is_html},
+  ignore::retain,
+  is_md,
+  md::md_edits,
+  project::{Item, run_edits},
+  subtitle::{Subtitle, subtitle_edits},
+  translation::{Translation, translation_edits},
+};
+
+/// `txt` found at byte `start`, as one run.
+pub(crate) fn items(txt: &str, start: usize) -> Vec<Item> {
+  txt
+    .char_indices()
+    .map(|(i, c)| Item {
+      c,
+      at: start + i,
+      pos: Some(start + i),
+    })
+    .collect()
+}
+
+/// How a document is parsed before spacing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "conf", derive(serde::Deserialize))]
+#[cfg_attr(feature = "conf", serde(rename_all = "lowercase"))]
+pub enum Mode {
+  /// Plain text, line by line
+  #[default]
+  Text,
+  Markdown,
+  /// HTML, only text nodes (and [`Options::html_attributes`]) are spaced, `script`, `style`, `pre`
+  /// and `code` are kept as is
+  Html,
+  /// Source code, only comments (and string literals with [`Options::strings`]) are spaced
+  #[cfg_attr(feature = "conf", serde(untagged))]
+  Code(Lang),
+  /// Subtitles, only cue text is spaced, indices, timings and style tags are kept as is
+  #[cfg_attr(feature = "conf", serde(untagged))]
+  Subtitle(Subtitle),
+  /// JSON, YAML or TOML, only string values are spaced, keys, comments and ICU placeholders like
+  /// `{count}` are kept as is
+  #[cfg_attr(feature = "conf", serde(untagged))]
+  Data(Data),
+  /// Translation files, only `msgstr` and `<target>` text is spaced, source strings and printf
+  /// conversions like `%s` are kept as is
+  #[cfg_attr(feature = "conf", serde(untagged))]
+  Translation(Translation),
+}
+
+impl Mode {
+  /// File extensions of plain text and Markdown.
+  pub const EXTENSIONS: &[&str] = &["md", "markdown", "mdt", "txt"];
+
+  /// Mode of a file whose extension is known, `None` for others, which [`Mode::from_path`] takes
+  /// as text.
+  pub fn from_known_path(path: impl AsRef<Path>) -> Option<Self> {
+    let path = path.as_ref();
+    let text = path
+      .extension()
+      .and_then(|e| e.to_str())
+      .is_some_and(|e| Self::EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()));
+    match Self::from_path(path) {
+      Mode::Text if !text => None,
+      mode => Some(mode),
+    }
+  }
+
+  pub fn from_path(path: impl AsRef<Path>) -> Self {
+    let path = path.as_ref();
+    if is_md(path) {
+      Mode::Markdown
+    } else if is_html(path) {
+      Mode::Html
+    } else if let Some(lang) = Lang::from_path(path) {
+      Mode::Code(lang)
+    } else if let Some(sub) = Subtitle::from_path(path) {
+      Mode::Subtitle(sub)
+    } else if let Some(data) = Data::from_path(path) {
+      Mode::Data(data)
+    } else if let Some(t) = Translation::from_path(path) {
+      Mode::Translation(t)
+    } else {
+      Mode::Text
+    }
+  }
+
+  /// Changes formatting makes to a whole document, sorted. Lines kept by ignore directives like
+  /// `<!-- add_space-ignore-next-line -->` are left out.
+  pub fn edits(self, txt: &str, opts: &Options) -> Vec<Edit> {
+    let mut r = match self {
+      Mode::Text => {
+        let mut r = Vec::new();
+        let mut start = 0;
+        for line in txt.split_inclusive('\n') {
+          let body = line.trim_end_matches(['\n', '\r']);
+          run_edits(&items(body, start), opts, &mut r);
+          start += line.len();
+        }
+        set_char_range(txt, &mut r);
+        r
+      }
+      Mode::Markdown => md_edits(txt, opts),
+      Mode::Html => html_edits(txt, opts),
+      Mode::Code(lang) => code_edits(txt, lang, opts),
+      Mode::Subtitle(sub) => subtitle_edits(txt, sub, opts),
+      Mode::Data(data) => data_edits(txt, data, opts),
+      Mode::Translation(t) => translation_edits(txt, t, opts),
+    };
+    retain(txt, &mut r);
+    r
+  }
+
+  /// Formats a whole document, line terminators, the final newline and a leading BOM are kept as is.
+  pub fn format(self, txt: &str, opts: &Options) -> String {
+    apply_edits(txt, &self.edits(txt, opts))
+  }
+}
@@ -0,0 +1,332 @@
+use unicode_script::{Script, UnicodeScript};
+
+use regex::Regex;
+
+use crate::{Placeholder, Protect, State, UNITS};
+
+pub const SCRIPTS: [Script; 11] = [
+  Script::Han,
+  Script::Hiragana,
+  Script::Katakana,
+  Script::Bopomofo,
+  Script::Yi,
+  Script::Hangul,
+  Script::Thai,
+  Script::Lao,
+  Script::Khmer,
+  Script::Myanmar,
+  Script::Tibetan,
+];
+
+/// How a script in [`Options::scripts`] is spaced against Latin words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScriptRule {
+  /// Space between the script and a following word, `中文English`
+  pub before_word: bool,
+  /// Space between a word and the script, `English中文`
+  pub after_word: bool,
+  /// Takes full-width punctuation, see [`Options::fullwidth_punctuation`]
+  pub fullwidth_punctuation: bool,
+}
+
+impl ScriptRule {
+  /// Chinese and Japanese
+  pub const CJK: Self = Self {
+    before_word: true,
+    after_word: true,
+    fullwidth_punctuation: true,
+  };
+  /// Scripts written without spaces but with Latin punctuation, like Thai
+  pub const HALFWIDTH: Self = Self {
+    before_word: true,
+    after_word: true,
+    fullwidth_punctuation: false,
+  };
+  /// Korean already separates words, and particles stick to Latin words: `iPhone을`
+  pub const KOREAN: Self = Self {
+    before_word: false,
+    after_word: false,
+    fullwidth_punctuation: false,
+  };
+}
+
+/// Rules of [`SCRIPTS`], scripts missing here follow [`ScriptRule::CJK`].
+pub const SCRIPT_RULES: [(Script, ScriptRule); 6] = [
+  (Script::Hangul, ScriptRule::KOREAN),
+  (Script::Thai, ScriptRule::HALFWIDTH),
+  (Script::Lao, ScriptRule::HALFWIDTH),
+  (Script::Khmer, ScriptRule::HALFWIDTH),
+  (Script::Myanmar, ScriptRule::HALFWIDTH),
+  (Script::Tibetan, ScriptRule::HALFWIDTH),
+];
+
+/// Script of `c` among `scripts`, also for common letters like `ー` whose script extensions name
+/// one of them. Brackets like `【》` list Han too but stay punctuation.
+pub(crate) fn script_of(scripts: &[Script], c: char) -> Option<Script> {
+  let script = c.script();
+  if scripts.contains(&script) {
+    return Some(script);
+  }
+  if !c.is_alphanumeric() {
+    return None;
+  }
+  let ext = c.script_extension();
+  if ext.is_common() || ext.is_inherited() {
+    return None;
+  }
+  ext.iter().find(|s| scripts.contains(s))
+}
+
+pub const PUNCTUATION: &str =
+  r##"!"#%\'*+,-.:<=>?@^·—‘’“”…、。「」『』〈〉《》【】〔〕〖〗〘〙〚〛！，：？；（）"##;
+
+/// Spacing rules, `Options::default()` gives the same output as [`crate::add_space`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+  /// Scripts treated as CJK-like `State::Char`
+  pub scripts: Vec<Script>,
+  /// Per script rules, [`ScriptRule::CJK`] for scripts not listed
+  pub script_rules: Vec<(Script, ScriptRule)>,
+  /// Full-width digits `０`-`９` count as `State::Char`
+  pub fullwidth_digit: bool,
+  /// Chars classified as `State::Punctuation`
+  pub punctuation: String,
+  /// Multi-byte emoji count as `State::Punctuation`
+  pub emoji: bool,
+  /// Space between `,?!…` and a following word
+  pub space_after_punctuation: bool,
+  /// Space between `.` and a following capitalized word
+  pub space_after_period: bool,
+  /// Half-width `,.;:!?()` in CJK text become full-width, `中文,中文` -> `中文，中文`
+  pub fullwidth_punctuation: bool,
+  /// Full-width punctuation in Latin text becomes half-width, `word，word` -> `word, word`
+  pub halfwidth_punctuation: bool,
+  /// Full-width letters and digits become ASCII and are spaced as such, `中文ＡＢＣ１２３` ->
+  /// `中文 ABC123`
+  pub halfwidth_alphanumeric: bool,
+  /// Space between a number and its unit: `Some(true)` gives `10 GB`, `Some(false)` gives `10GB`,
+  /// `None` leaves them alone. [`crate::ATTACHED_UNITS`] like `%` and `°C` always stick to the
+  /// number
+  pub unit_space: Option<bool>,
+  /// Units recognized by [`Options::unit_space`]
+  pub units: Vec<String>,
+  /// [`crate::Mode::Code`] also spaces string literals, not only comments
+  pub strings: bool,
+  /// Attributes whose values [`crate::Mode::Html`] also spaces, like `alt` and `title`
+  pub html_attributes: Vec<String>,
+  /// [`crate::Mode::Data`] only spaces values under these keys, all when empty. A key is a dotted
+  /// path like `home.title` matching at any depth, `*` standing for any one key
+  pub keys: Vec<String>,
+  /// [`crate::Mode::Data`] leaves values under these keys alone, same syntax as [`Options::keys`]
+  pub skip_keys: Vec<String>,
+  /// Template placeholders kept whole, all of [`Placeholder::ALL`] by default. Of two syntaxes
+  /// matching at the same char the one listed first wins
+  pub placeholders: Vec<Placeholder>,
+  /// Space between a placeholder and the text around it: `Some(true)` spaces it like a word,
+  /// `共有 {count} 个`, `Some(false)` removes the spaces next to CJK text, `共有{count}个`, `None`
+  /// leaves them alone, the default: kept whole, the text around spaced as without placeholders
+  pub placeholder_space: Option<bool>,
+  /// Spans kept whole, spaced around like a word, [`Protect::BUILTIN`] by default. Of overlapping
+  /// matches the kind listed first wins
+  pub protect: Vec<Protect>,
+  /// Spaces between CJK chars and next to full-width punctuation are removed, `中 文 ，` ->
+  /// `中文，`, see [`crate::normalize`]
+  pub remove_space: bool,
+}
+
+impl Default for Options {
+  fn default() -> Self {
+    Self {
+      scripts: SCRIPTS.to_vec(),
+      script_rules: SCRIPT_RULES.to_vec(),
+      fullwidth_digit: true,
+      punctuation: PUNCTUATION.into(),
+      emoji: true,
+      space_after_punctuation: true,
+      space_after_period: true,
+      fullwidth_punctuation: false,
+      halfwidth_punctuation: false,
+      halfwidth_alphanumeric: false,
+      unit_space: None,
+      units: UNITS.iter().map(|u| u.to_string()).collect(),
+      strings: false,
+      html_attributes: Vec::new(),
+      keys: Vec::new(),
+      skip_keys: Vec::new(),
+      placeholders: Placeholder::ALL.to_vec(),
+      placeholder_space: None,
+      protect: Protect::BUILTIN.to_vec(),
+      remove_space: false,
+    }
+  }
+}
+
+impl Options {
+  pub fn scripts(mut self, scripts: impl IntoIterator<Item = Script>) -> Self {
+    self.scripts = scripts.into_iter().collect();
+    self
+  }
+
+  /// Sets the rule of `script`, keeping the others.
+  pub fn script_rule(mut self, script: Script, rule: ScriptRule) -> Self {
+    self.script_rules.retain(|(s, _)| *s != script);
+    self.script_rules.push((script, rule));
+    self
+  }
+
+  pub fn fullwidth_digit(mut self, on: bool) -> Self {
+    self.fullwidth_digit = on;
+    self
+  }
+
+  pub fn punctuation(mut self, punctuation: impl Into<String>) -> Self {
+    self.punctuation = punctuation.into();
+    self
+  }
+
+  pub fn emoji(mut self, on: bool) -> Self {
+    self.emoji = on;
+    self
+  }
+
+  pub fn space_after_punctuation(mut self, on: bool) -> Self {
+    self.space_after_punctuation = on;
+    self
+  }
+
+  pub fn space_after_period(mut self, on: bool) -> Self {
+    self.space_after_period = on;
+    self
+  }
+
+  pub fn fullwidth_punctuation(mut self, on: bool) -> Self {
+    self.fullwidth_punctuation = on;
+    self
+  }
+
+  pub fn halfwidth_punctuation(mut self, on: bool) -> Self {
+    self.halfwidth_punctuation = on;
+    self
+  }
+
+  pub fn halfwidth_alphanumeric(mut self, on: bool) -> Self {
+    self.halfwidth_alphanumeric = on;
+    self
+  }
+
+  pub fn unit_space(mut self, on: bool) -> Self {
+    self.unit_space = Some(on);
+    self
+  }
+
+  pub fn units(mut self, units: impl IntoIterator<Item = impl Into<String>>) -> Self {
+    self.units = units.into_iter().map(Into::into).collect();
+    self
+  }
+
+  /// Adds a unit to [`Options::units`].
+  pub fn unit(mut self, unit: impl Into<String>) -> Self {
+    self.units.push(unit.into());
+    self
+  }
+
+  pub fn strings(mut self, on: bool) -> Self {
+    self.strings = on;
+    self
+  }
+
+  pub fn html_attributes(mut self, names: impl IntoIterator<Item = impl Into<String>>) -> Self {
+    self.html_attributes = names.into_iter().map(Into::into).collect();
+    self
+  }
+
+  pub fn keys(mut self, keys: impl IntoIterator<Item = impl Into<String>>) -> Self {
+    self.keys = keys.into_iter().map(Into::into).collect();
+    self
+  }
+
+  pub fn skip_keys(mut self, keys: impl IntoIterator<Item = impl Into<String>>) -> Self {
+    self.skip_keys = keys.into_iter().map(Into::into).collect();
+    self
+  }
+
+  pub fn placeholders(mut self, placeholders: impl IntoIterator<Item = Placeholder>) -> Self {
+    self.placeholders = placeholders.into_iter().collect();
+    self
+  }
+
+  /// Adds a syntax to [`Options::placeholders`] when missing.
+  pub fn placeholder(mut self, placeholder: Placeholder) -> Self {
+    if !self.placeholders.contains(&placeholder) {
+      self.placeholders.push(placeholder);
+    }
+    self
+  }
+
+  pub fn placeholder_space(mut self, on: bool) -> Self {
+    self.placeholder_space = Some(on);
+    self
+  }
+
+  pub fn protect(mut self, protect: impl IntoIterator<Item = Protect>) -> Self {
+    self.protect = protect.into_iter().collect();
+    self
+  }
+
+  /// Adds a user pattern to [`Options::protect`].
+  pub fn protect_regex(mut self, re: Regex) -> Self {
+    self.protect.push(Protect::Regex(re));
+    self
+  }
+
+  pub fn remove_space(mut self, on: bool) -> Self {
+    self.remove_space = on;
+    self
+  }
+
+  /// Whether spacing looks at whole lines: chars may be replaced or removed, or spans protected or
+  /// kept as placeholders.
+  pub(crate) fn whole_line(&self) -> bool {
+    self.fullwidth_punctuation
+      || self.halfwidth_punctuation
+      || self.halfwidth_alphanumeric
+      || self.unit_space.is_some()
+      || self.remove_space
+      || !self.protect.is_empty()
+      || !self.placeholders.is_empty()
+  }
+
+  /// [`crate::state`] under these options.
+  pub fn state(&self, c: char) -> State {
+    if c.is_whitespace() {
+      return State::Space;
+    }
+    if self.scripts.contains(&c.script()) || (self.fullwidth_digit && ('０'..='９').contains(&c))
+    {
+      return State::Char;
+    }
+
+    if c == '`' {
+      return State::Letter;
+    }
+
+    if self.punctuation.contains(c)
+      || (self.emoji && c.len_utf8() > 1 && unic_emoji_char::is_emoji(c))
+    {
+      return State::Punctuation;
+    }
+
+    if c.len_utf8() > 1 && script_of(&self.scripts, c).is_some() {
+      return State::Char;
+    }
+
+    State::Letter
+  }
+
+  /// Rule for a `State::Char` char, full-width digits follow [`ScriptRule::CJK`].
+  pub fn rule_of(&self, c: char) -> ScriptRule {
+    script_of(&self.scripts, c)
+      .and_then(|script| self.script_rules.iter().find(|(s, _)| *s == script))
+      .map_or(ScriptRule::CJK, |(_, rule)| *rule)
+  }
+}
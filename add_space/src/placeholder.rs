@@ -0,0 +1,265 @@
+use std::ops::Range;
+
+use crate::{
+  Edit, Options, Rule, State,
+  project::Item,
+  remove::{self, is_fullwidth_punctuation},
+};
+
+/// Template placeholder syntax, kept whole and spaced per [`Options::placeholder_space`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "conf", derive(serde::Deserialize))]
+#[cfg_attr(feature = "conf", serde(rename_all = "lowercase"))]
+pub enum Placeholder {
+  /// Mustache and Handlebars, `{{name}}`, `{{{html}}}`
+  Mustache,
+  /// Shell and JavaScript template literals, `${name}`
+  Shell,
+  /// ICU MessageFormat, `{0}`, `{count}`, `{n, plural, one {# item} other {# items}}`, the
+  /// argument name an ASCII identifier
+  Icu,
+  /// printf, `%s`, `%1$d`, `%-5.2f`, Python's `%(name)s`. Not after a digit, `20%off` is none
+  Printf,
+}
+
+/// Argument types of ICU MessageFormat, `{n, plural, …}`.
+const ICU_TYPES: &[&str] = &[
+  "number",
+  "date",
+  "time",
+  "plural",
+  "select",
+  "selectordinal",
+  "spellout",
+  "ordinal",
+  "duration",
+];
+
+/// Length of the balanced braces `s` starts with.
+fn braces(s: &[char]) -> Option<usize> {
+  let mut depth = 0;
+  for (i, c) in s.iter().enumerate() {
+    match c {
+      '{' => depth += 1,
+      '}' => {
+        depth -= 1;
+        if depth == 0 {
+          return Some(i + 1);
+        }
+      }
+      _ => {}
+    }
+  }
+  None
+}
+
+/// Length of the printf conversion `s` starts with.
+fn printf(s: &[char]) -> Option<usize> {
+  let digits = |i: usize| {
+    s[i.min(s.len())..]
+      .iter()
+      .take_while(|c| c.is_ascii_digit())
+      .count()
+  };
+  let mut i = 1;
+  if s.get(1) == Some(&'(') {
+    i = s.iter().take(64).position(|c| *c == ')')? + 1;
+  } else {
+    let n = digits(1);
+    if n > 0 && s.get(1 + n) == Some(&'$') {
+      i += n + 1;
+    }
+  }
+  // flags, a space is left out as `50% done` is no conversion
+  while matches!(s.get(i), Some('-' | '+' | '#' | '0' | '\'')) {
+    i += 1;
+  }
+  if s.get(i) == Some(&'*') {
+    i += 1;
+  } else {
+    i += digits(i);
+  }
+  if s.get(i) == Some(&'.') {
+    i += 1;
+    if s.get(i) == Some(&'*') {
+      i += 1;
+    } else {
+      i += digits(i);
+    }
+  }
+  while matches!(s.get(i), Some('h' | 'l' | 'L' | 'q' | 'j' | 'z' | 't')) {
+    i += 1;
+  }
+  s.get(i)
+    .is_some_and(|c| "diouxXeEfFgGaAcspn@".contains(*c))
+    .then_some(i + 1)
+}
+
+impl Placeholder {
+  pub const ALL: [Placeholder; 4] = [
+    Placeholder::Mustache,
+    Placeholder::Shell,
+    Placeholder::Icu,
+    Placeholder::Printf,
+  ];
+
+  /// Length in chars of the placeholder at `chars[i]`.
+  fn len(self, chars: &[char], i: usize) -> Option<usize> {
+    let s = &chars[i..];
+    match self {
+      Placeholder::Mustache => {
+        let open = s.iter().take(3).take_while(|c| **c == '{').count();
+        if open < 2 {
+          return None;
+        }
+        let close = vec!['}'; open];
+        let end = (open + 1..s.len()).find(|&e| s[e..].starts_with(&close))?;
+        Some(end + open)
+      }
+      Placeholder::Shell => {
+        if !s.starts_with(&['$', '{']) {
+          return None;
+        }
+        braces(&s[1..]).map(|len| len + 1)
+      }
+      Placeholder::Icu => {
+        if s.first() != Some(&'{') {
+          return None;
+        }
+        let space = |i: usize| s[i..].iter().take_while(|c| **c == ' ').count();
+        let mut i = 1 + space(1);
+        let name = s[i..]
+          .iter()
+          .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+          .count();
+        if name == 0 {
+          return None;
+        }
+        i += name;
+        i += space(i);
+        match s.get(i)? {
+          '}' => Some(i + 1),
+          ',' => {
+            i += 1 + space(i + 1);
+            let kind: String = s[i..]
+              .iter()
+              .take_while(|c| c.is_ascii_alphabetic())
+              .collect();
+            ICU_TYPES.contains(&kind.as_str()).then(|| braces(s))?
+          }
+          _ => None,
+        }
+      }
+      Placeholder::Printf => {
+        if s.first() != Some(&'%') || s.get(1) == Some(&'%') {
+          return None;
+        }
+        if i > 0 && (chars[i - 1].is_ascii_digit() || chars[i - 1] == '%') {
+          return None;
+        }
+        printf(s)
+      }
+    }
+  }
+}
+
+/// Replaces every placeholder of `run` (chars with their width fix) by a stand-in: an atom spaced
+/// like a word when [`Options::placeholder_space`] is `Some(true)`, a space no rule fires next to
+/// with `Some(false)`, which also deletes the spaces between a placeholder and CJK text into `out`.
+/// With `None` the run is left as is and the byte ranges of the placeholders, no edit may fall
+/// into, are returned.
+pub(crate) fn placeholders(
+  run: &mut Vec<(Item, Option<(char, Rule)>)>,
+  opts: &Options,
+  out: &mut Vec<Edit>,
+) -> Vec<Range<usize>> {
+  if opts.placeholders.is_empty() {
+    return Vec::new();
+  }
+  let chars: Vec<char> = run
+    .iter()
+    .map(|(item, fix)| fix.map_or(item.c, |(c, _)| c))
+    .collect();
+  let mut spans = Vec::new();
+  let mut i = 0;
+  while i < chars.len() {
+    match opts.placeholders.iter().find_map(|p| p.len(&chars, i)) {
+      Some(len) => {
+        spans.push(i..i + len);
+        i += len;
+      }
+      None => i += 1,
+    }
+  }
+  let Some(space) = opts.placeholder_space else {
+    return spans
+      .iter()
+      .filter_map(|span| {
+        let (first, last) = (run[span.start].0, run[span.end - 1].0);
+        Some(first.pos?..last.pos? + last.c.len_utf8())
+      })
+      .collect();
+  };
+  if spans.is_empty() {
+    return Vec::new();
+  }
+
+  // written without spaces, see `remove_fixes`
+  let unspaced = |c: char| {
+    (opts.state(c) == State::Char && opts.rule_of(c).fullwidth_punctuation)
+      || is_fullwidth_punctuation(c)
+  };
+  let is_space = |n: usize| remove::is_space(chars[n]) && run[n].0.pos.is_some();
+  let mut deleted = vec![false; run.len()];
+  if !space {
+    for span in &spans {
+      let mut start = span.start;
+      while start > 0 && is_space(start - 1) {
+        start -= 1;
+      }
+      if start < span.start && start > 0 && unspaced(chars[start - 1]) {
+        deleted[start..span.start].fill(true);
+      }
+      let mut end = span.end;
+      while end < chars.len() && is_space(end) {
+        end += 1;
+      }
+      if end > span.end && end < chars.len() && unspaced(chars[end]) {
+        deleted[span.end..end].fill(true);
+      }
+    }
+  }
+
+  let mut r = Vec::with_capacity(run.len());
+  let mut spans = spans.into_iter().peekable();
+  let mut n = 0;
+  while n < run.len() {
+    if let Some(span) = spans.next_if(|s| s.start == n) {
+      let at = run[n].0.at;
+      if space {
+        r.extend(Item::atom(at).map(|item| (item, None)));
+      } else {
+        r.push((
+          Item {
+            c: ' ',
+            at,
+            pos: None,
+          },
+          None,
+        ));
+      }
+      n = span.end;
+      continue;
+    }
+    if deleted[n] {
+      let pos = run[n].0.pos.unwrap_or_default();
+      let len = run[n].0.c.len_utf8();
+      out.push(Edit::replace(pos..pos + len, "", Rule::ExtraSpace));
+    } else {
+      r.push(run[n]);
+    }
+    n += 1;
+  }
+  *run = r;
+  Vec::new()
+}
@@ -0,0 +1,178 @@
+use std::ops::Range;
+
+use crate::{
+  Edit, Options, Rule, Spacer, edit::set_char_range, placeholder::placeholders, protect::protect,
+  remove::remove_fixes, unit::unit_fixes, width::normalize,
+};
+
+/// A char of projected prose.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Item {
+  pub c: char,
+  /// Where a space inserted before `c` lands
+  pub at: usize,
+  /// Offset of `c` itself, `None` for the stand-in chars of an atom
+  pub pos: Option<usize>,
+}
+
+impl Item {
+  /// Stand-in chars of an opaque span, a space before it lands at `at`.
+  pub fn atom(at: usize) -> [Item; 3] {
+    let item = |c| Item { c, at, pos: None };
+    [item('`'), item('x'), item('`')]
+  }
+}
+
+/// Whitespace change to a run decided before spacing, in char indices of the run.
+#[derive(Debug, Clone)]
+pub(crate) enum Fix {
+  /// Space before the char
+  Space(usize, Rule),
+  /// Chars removed
+  Delete(Range<usize>, Rule),
+}
+
+/// Spaces, width and unit fixes for one run of chars, spacing state starts fresh. The edits pushed
+/// to `out` are sorted.
+pub(crate) fn run_edits(run: &[Item], opts: &Options, out: &mut Vec<Edit>) {
+  let start = out.len();
+  let chars: Vec<char> = run.iter().map(|item| item.c).collect();
+  let mut fixed: Vec<_> = run.iter().copied().zip(normalize(&chars, opts)).collect();
+  let kept = placeholders(&mut fixed, opts, out);
+  protect(&mut fixed, opts);
+  let (run, fixed): (Vec<Item>, Vec<_>) = fixed.into_iter().unzip();
+  let chars: Vec<char> = run
+    .iter()
+    .zip(&fixed)
+    .map(|(item, fix)| fix.map_or(item.c, |(c, _)| c))
+    .collect();
+
+  // the text as the spacer sees it once whitespace fixes are applied, `None` for an added space
+  let mut space = vec![false; run.len()];
+  let mut deleted = vec![false; run.len()];
+  for fix in unit_fixes(&chars, opts)
+    .into_iter()
+    .chain(remove_fixes(&run, &chars, opts))
+  {
+    match fix {
+      Fix::Space(i, rule) => {
+        space[i] = true;
+        out.push(Edit::space(run[i].at, rule));
+      }
+      Fix::Delete(range, rule) => {
+        if let (Some(first), Some(last)) = (run[range.start].pos, run[range.end - 1].pos) {
+          deleted[range.clone()].fill(true);
+          let end = last + run[range.end - 1].c.len_utf8();
+          out.push(Edit::replace(first..end, "", rule));
+        }
+      }
+    }
+  }
+  let mut seen = Vec::with_capacity(run.len());
+  for i in 0..run.len() {
+    if space[i] {
+      seen.push((' ', None));
+    }
+    if !deleted[i] {
+      seen.push((chars[i], Some(i)));
+    }
+  }
+
+  let mut spacer = Spacer::new(opts);
+  for (n, &(c, i)) in seen.iter().enumerate() {
+    let rule = spacer.rule(c, seen.get(n + 1).map(|(c, _)| *c));
+    let Some(i) = i else {
+      continue;
+    };
+    let item = &run[i];
+    if let Some(rule) = rule {
+      out.push(Edit::space(item.at, rule));
+    }
+    if let (Some((c, rule)), Some(pos)) = (fixed[i], item.pos) {
+      out.push(Edit::replace(pos..pos + item.c.len_utf8(), c, rule));
+    }
+  }
+  if !kept.is_empty() {
+    // a space may go before a placeholder left alone, nothing inside it
+    let mut edits = out.split_off(start);
+    edits.retain(|e| {
+      !kept
+        .iter()
+        .any(|r| r.contains(&e.range.start) && (e.range.start > r.start || !e.range.is_empty()))
+    });
+    out.extend(edits);
+  }
+  out[start..].sort_by_key(|e| (e.range.start, e.range.end));
+}
+
+/// Prose projected out of a structured document, every char keeps the source offset where a space
+/// inserted before it lands.
+#[derive(Debug, Default)]
+pub(crate) struct Project {
+  runs: Vec<Vec<Item>>,
+  anchor: Option<usize>,
+}
+
+impl Project {
+  fn run(&mut self) -> &mut Vec<Item> {
+    if self.runs.is_empty() {
+      self.runs.push(Vec::new());
+    }
+    self.runs.last_mut().unwrap()
+  }
+
+  fn at(&mut self, pos: usize) -> usize {
+    self.anchor.take().unwrap_or(pos)
+  }
+
+  /// Opening markup at `pos`: a space before the next prose char goes before the markup.
+  pub fn anchor(&mut self, pos: usize) {
+    if self.anchor.is_none() {
+      self.anchor = Some(pos);
+    }
+  }
+
+  pub fn text(&mut self, src: &str, range: Range<usize>) {
+    let start = range.start;
+    for (i, c) in src[range].char_indices() {
+      let pos = start + i;
+      let at = if i == 0 { self.at(pos) } else { pos };
+      self.run().push(Item {
+        c,
+        at,
+        pos: Some(pos),
+      });
+    }
+  }
+
+  /// `c` written as the span at `range`, like an HTML entity: spaced as `c`, never replaced.
+  pub fn escaped(&mut self, c: char, range: Range<usize>) {
+    let at = self.at(range.start);
+    self.run().push(Item { c, at, pos: None });
+  }
+
+  /// Opaque span, spaced around like a word but never touched inside.
+  pub fn atom(&mut self, range: Range<usize>) {
+    let at = self.at(range.start);
+    self.run().extend(Item::atom(at));
+  }
+
+  /// Structural boundary, spacing state does not carry over it.
+  pub fn brk(&mut self) {
+    self.anchor = None;
+    if self.runs.last().is_none_or(|r| !r.is_empty()) {
+      self.runs.push(Vec::new());
+    }
+  }
+
+  /// Changes to `src`, sorted.
+  pub fn edits(&self, src: &str, opts: &Options) -> Vec<Edit> {
+    let mut r: Vec<Edit> = Vec::new();
+    for run in &self.runs {
+      run_edits(run, opts, &mut r);
+    }
+    set_char_range(src, &mut r);
+    r.dedup_by(|a, b| a.range == b.range);
+    r
+  }
+}
@@ -0,0 +1,165 @@
+use std::{ops::Range, sync::LazyLock};
+
+use regex::Regex;
+
+use crate::{Options, Rule, project::Item};
+
+/// Kind of span kept whole, spaced around like a word but never changed inside.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "conf", derive(serde::Deserialize))]
+#[cfg_attr(feature = "conf", serde(rename_all = "lowercase"))]
+pub enum Protect {
+  /// `https://example.com/a?b=1,c`
+  Url,
+  /// `john.smith@example.com`
+  Email,
+  /// `@user`, `@types/react`
+  Mention,
+  /// `#topic`
+  Hashtag,
+  /// `1.2.3`, `v2.0.0-beta.1+build.5`
+  Semver,
+  /// `~/Documents`, `./src/main.rs`, `/usr/local/bin/`
+  Path,
+  /// Matches of a user pattern
+  #[cfg_attr(feature = "conf", serde(skip_deserializing))]
+  Regex(Regex),
+}
+
+impl PartialEq for Protect {
+  fn eq(&self, other: &Self) -> bool {
+    match (self, other) {
+      (Self::Regex(a), Self::Regex(b)) => a.as_str() == b.as_str(),
+      _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+    }
+  }
+}
+
+static URL: LazyLock<Regex> =
+  LazyLock::new(|| Regex::new(r#"[A-Za-z][A-Za-z0-9+.-]*://[!-~&&[^<>"'`]]+"#).unwrap());
+static EMAIL: LazyLock<Regex> =
+  LazyLock::new(|| Regex::new(r"[A-Za-z0-9_.%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)+").unwrap());
+static MENTION: LazyLock<Regex> =
+  LazyLock::new(|| Regex::new(r"@[A-Za-z0-9_][A-Za-z0-9_.-]*(?:/[A-Za-z0-9_.-]+)?").unwrap());
+static HASHTAG: LazyLock<Regex> = LazyLock::new(|| Regex::new("#[A-Za-z_][A-Za-z0-9_]*").unwrap());
+static SEMVER: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r"v?[0-9]+\.[0-9]+\.[0-9]+(?:-[0-9A-Za-z.-]+)?(?:\+[0-9A-Za-z.-]+)?").unwrap()
+});
+static PATH: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(concat!(
+    r"(?:~|\.\.?)?(?:/[A-Za-z0-9_.-]+)+/?",
+    r"|[A-Za-z0-9_.-]+(?:/[A-Za-z0-9_.-]+)+/?",
+    r"|[A-Za-z]:\\[A-Za-z0-9_.\\-]+",
+  ))
+  .unwrap()
+});
+
+impl Protect {
+  /// Kinds protected by default, all but user patterns.
+  pub const BUILTIN: [Protect; 6] = [
+    Protect::Url,
+    Protect::Email,
+    Protect::Mention,
+    Protect::Path,
+    Protect::Semver,
+    Protect::Hashtag,
+  ];
+
+  fn regex(&self) -> &Regex {
+    match self {
+      Protect::Url => &URL,
+      Protect::Email => &EMAIL,
+      Protect::Mention => &MENTION,
+      Protect::Hashtag => &HASHTAG,
+      Protect::Semver => &SEMVER,
+      Protect::Path => &PATH,
+      Protect::Regex(re) => re,
+    }
+  }
+
+  /// Cuts trailing sentence punctuation off a built-in match, `None` when it is part of a word.
+  fn trim(&self, txt: &str, m: Range<usize>) -> Option<Range<usize>> {
+    if let Protect::Regex(_) = self {
+      return (!m.is_empty()).then_some(m);
+    }
+    let pre = txt[..m.start].chars().next_back();
+    if pre.is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+      || (matches!(self, Protect::Semver) && pre == Some('.'))
+    {
+      return None;
+    }
+    let mut s = &txt[m.clone()];
+    loop {
+      s = s.trim_end_matches(['.', ',', ';', ':', '!', '?', '-']);
+      // a closing parenthesis belongs to the match only when it opened inside
+      match s.strip_suffix(')') {
+        Some(t) if t.matches('(').count() < s.matches(')').count() => s = t,
+        _ => break,
+      }
+    }
+    let end = m.start + s.len();
+    let mut next = txt[end..].chars();
+    match self {
+      Protect::Path if !s.contains(['/', '\\']) => None,
+      // 1.2.3.4 is an address, not a version
+      Protect::Semver
+        if next.next() == Some('.') && next.next().is_some_and(|c| c.is_ascii_digit()) =>
+      {
+        None
+      }
+      _ => (end > m.start).then_some(m.start..end),
+    }
+  }
+}
+
+/// Protected spans of `chars` in char indices, sorted and disjoint. Of overlapping matches the kind
+/// listed first in [`Options::protect`] wins, so spaces added around a span do not change it.
+pub(crate) fn spans(chars: &[char], opts: &Options) -> Vec<Range<usize>> {
+  let txt: String = chars.iter().collect();
+  let mut found: Vec<Range<usize>> = Vec::new();
+  for p in &opts.protect {
+    let mut at = 0;
+    while at <= txt.len()
+      && let Some(m) = p.regex().find_at(&txt, at)
+    {
+      match p.trim(&txt, m.range()) {
+        Some(m) => {
+          at = m.end;
+          if found.iter().all(|f| f.end <= m.start || m.end <= f.start) {
+            found.push(m);
+          }
+        }
+        // a later match may start inside a rejected one
+        None => at = m.start() + txt[m.start()..].chars().next().map_or(1, char::len_utf8),
+      }
+    }
+  }
+  found.sort_by_key(|m| m.start);
+
+  let offsets: Vec<usize> = txt.char_indices().map(|(i, _)| i).collect();
+  let index = |b: usize| offsets.partition_point(|&o| o < b);
+  found
+    .into_iter()
+    .map(|m| index(m.start)..index(m.end))
+    .collect()
+}
+
+/// Replaces every protected span of `run` (chars with their width fix) by an atom stand-in, the
+/// fixes inside are dropped. Spans are found in the text as width fixes leave it, so that a second
+/// pass finds the same spans.
+pub(crate) fn protect(run: &mut Vec<(Item, Option<(char, Rule)>)>, opts: &Options) {
+  if opts.protect.is_empty() {
+    return;
+  }
+  let chars: Vec<char> = run
+    .iter()
+    .map(|(item, fix)| fix.map_or(item.c, |(c, _)| c))
+    .collect();
+  for span in spans(&chars, opts).into_iter().rev() {
+    // spans running into an atom of the document stay prose
+    if run[span.clone()].iter().all(|(item, _)| item.pos.is_some()) {
+      let at = run[span.start].0.at;
+      run.splice(span, Item::atom(at).map(|item| (item, None)));
+    }
+  }
+}
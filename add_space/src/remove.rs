@@ -0,0 +1,61 @@
+use crate::{
+  Options, Rule, State,
+  project::{Fix, Item},
+};
+
+/// Punctuation of the CJK Symbols and Punctuation and Halfwidth and Fullwidth Forms blocks, `，`,
+/// `。`, `「` and the like.
+pub(crate) fn is_fullwidth_punctuation(c: char) -> bool {
+  matches!(
+    c,
+    '\u{3001}'..='\u{3003}'
+      | '\u{3008}'..='\u{3011}'
+      | '\u{3014}'..='\u{301f}'
+      | '\u{ff01}'..='\u{ff0f}'
+      | '\u{ff1a}'..='\u{ff20}'
+      | '\u{ff3b}'..='\u{ff40}'
+      | '\u{ff5b}'..='\u{ff65}'
+  )
+}
+
+/// A space, ASCII or ideographic.
+pub(crate) fn is_space(c: char) -> bool {
+  c == ' ' || c == '\u{3000}'
+}
+
+/// Spaces of a run [`Options::remove_space`] deletes: between two chars of scripts written without
+/// spaces, and next to full-width punctuation. Leading and trailing spaces are kept, and so are
+/// stand-ins of a placeholder. Markdown keeps those an emphasis needs, see `md_edits`. `chars` are
+/// the run's chars after width fixes.
+pub(crate) fn remove_fixes(run: &[Item], chars: &[char], opts: &Options) -> Vec<Fix> {
+  let mut r = Vec::new();
+  if !opts.remove_space {
+    return r;
+  }
+  // Chinese and Japanese, Korean and Thai separate words with spaces
+  let unspaced = |c: char| opts.state(c) == State::Char && opts.rule_of(c).fullwidth_punctuation;
+  let mut i = 0;
+  while i < chars.len() {
+    if !is_space(chars[i]) {
+      i += 1;
+      continue;
+    }
+    let start = i;
+    while i < chars.len() && is_space(chars[i]) {
+      i += 1;
+    }
+    let (Some(&pre), Some(&next)) = (start.checked_sub(1).map(|p| &chars[p]), chars.get(i)) else {
+      continue;
+    };
+    if run[start].pos.is_none() || run[i - 1].pos.is_none() {
+      continue;
+    }
+    if (unspaced(pre) && unspaced(next))
+      || is_fullwidth_punctuation(pre)
+      || is_fullwidth_punctuation(next)
+    {
+      r.push(Fix::Delete(start..i, Rule::ExtraSpace));
+    }
+  }
+  r
+}
@@ -0,0 +1,300 @@
+use std::{
+  collections::VecDeque,
+  io::{self, Read, Write},
+  iter::Peekable,
+};
+
+use crate::{
+  Mode, Options, Spacer,
+  ignore::{HEAD, Ignore},
+};
+
+/// Longest piece of a line held back. A longer line is spaced in pieces cut after its last
+/// whitespace outside braces, placeholders and protected spans holding none, or at the limit
+/// when there is no such whitespace.
+const MAX_LINE: usize = 64 * 1024;
+
+/// Where a long line is cut: after its last whitespace outside braces.
+fn cut(line: &str) -> Option<usize> {
+  let mut depth = 0usize;
+  let mut r = None;
+  for (i, c) in line.char_indices() {
+    match c {
+      '{' => depth += 1,
+      '}' => depth = depth.saturating_sub(1),
+      c if c.is_whitespace() && depth == 0 => r = Some(i + c.len_utf8()),
+      _ => {}
+    }
+  }
+  r
+}
+
+/// The current line, held back whole (up to [`MAX_LINE`]) when [`Options`] look at whole lines,
+/// otherwise only its start, where an ignore directive may be.
+#[derive(Default)]
+struct Line {
+  txt: String,
+  /// start of a line already partly spaced
+  head: Option<String>,
+  ignore: Ignore,
+}
+
+impl Line {
+  /// Whether the current line is kept as is.
+  fn skips(&self) -> bool {
+    self.ignore.skips()
+  }
+
+  /// Notes `c` spaced char by char.
+  fn track(&mut self, c: char) {
+    if self.txt.len() < HEAD {
+      self.txt.push(c);
+    }
+    if c == '\n' {
+      self.ignore.line(&std::mem::take(&mut self.txt));
+    }
+  }
+
+  /// Adds `c`, returns the line it ends or the piece of a long line, spaced.
+  fn push(&mut self, c: char, opts: &Options) -> Option<String> {
+    self.txt.push(c);
+    if c == '\n' {
+      return Some(self.flush(self.txt.len(), true, opts));
+    }
+    if self.txt.len() < MAX_LINE {
+      return None;
+    }
+    let at = cut(&self.txt).unwrap_or(self.txt.len());
+    Some(self.flush(at, false, opts))
+  }
+
+  /// The rest of the last line, spaced.
+  fn finish(&mut self, opts: &Options) -> String {
+    self.flush(self.txt.len(), true, opts)
+  }
+
+  fn flush(&mut self, at: usize, end: bool, opts: &Options) -> String {
+    let piece: String = self.txt.drain(..at).collect();
+    if !end && self.head.is_none() {
+      let mut head = String::new();
+      for c in piece.chars() {
+        if head.len() >= HEAD {
+          break;
+        }
+        head.push(c);
+      }
+      self.head = Some(head);
+    }
+    let r = if self.skips() || piece.is_empty() {
+      piece.clone()
+    } else {
+      Mode::Text.format(&piece, opts)
+    };
+    if end {
+      let head = self.head.take();
+      self.ignore.line(head.as_deref().unwrap_or(&piece));
+    }
+    r
+  }
+}
+
+/// Iterator adapter inserting spaces into a stream of chars, see [`add_space_chars`].
+pub struct AddSpaceChars<'a, I: Iterator<Item = char>> {
+  opts: &'a Options,
+  spacer: Spacer<'a>,
+  iter: Peekable<I>,
+  pending: VecDeque<char>,
+  line: Line,
+}
+
+impl<I: Iterator<Item = char>> Iterator for AddSpaceChars<'_, I> {
+  type Item = char;
+
+  fn next(&mut self) -> Option<char> {
+    if let Some(c) = self.pending.pop_front() {
+      return Some(c);
+    }
+    if self.opts.whole_line() {
+      // replacements and protected spans look at the whole line
+      let spaced = loop {
+        match self.iter.next() {
+          Some(c) => {
+            if let Some(spaced) = self.line.push(c, self.opts) {
+              break spaced;
+            }
+          }
+          None => break self.line.finish(self.opts),
+        }
+      };
+      self.pending.extend(spaced.chars());
+      return self.pending.pop_front();
+    }
+    let c = self.iter.next()?;
+    let space = !self.line.skips() && self.spacer.push(c, self.iter.peek().copied());
+    self.line.track(c);
+    if c == '\n' {
+      self.spacer = Spacer::new(self.opts);
+    }
+    if space {
+      self.pending.push_back(c);
+      return Some(' ');
+    }
+    Some(c)
+  }
+}
+
+/// Lazily spaces a char stream line by line, the output equals [`crate::Mode::Text`] formatting.
+/// Like [`AddSpaceWriter`] it reads ahead up to a line, at most 64 KiB of it.
+pub fn add_space_chars<I: IntoIterator<Item = char>>(
+  iter: I,
+  opts: &Options,
+) -> AddSpaceChars<'_, I::IntoIter> {
+  AddSpaceChars {
+    opts,
+    spacer: Spacer::new(opts),
+    iter: iter.into_iter().peekable(),
+    pending: VecDeque::new(),
+    line: Line::default(),
+  }
+}
+
+/// [`Write`] adapter that spaces UTF-8 text on its way to `W`, holding back the current line, at
+/// most 64 KiB of it, and an incomplete UTF-8 sequence. [`Options::default`] needs whole lines as
+/// it protects URLs and keeps placeholders, a longer line is spaced in pieces cut after whitespace
+/// outside braces. With `protect`, `placeholders` and every char rewrite off at most one char is
+/// held back. Call [`AddSpaceWriter::finish`] to flush the rest, dropping the writer does it on a
+/// best effort basis.
+pub struct AddSpaceWriter<'a, W: Write> {
+  opts: &'a Options,
+  spacer: Spacer<'a>,
+  inner: Option<W>,
+  pending: Option<char>,
+  partial: Vec<u8>,
+  line: Line,
+  buf: String,
+}
+
+impl<'a, W: Write> AddSpaceWriter<'a, W> {
+  pub fn new(inner: W, opts: &'a Options) -> Self {
+    Self {
+      opts,
+      spacer: Spacer::new(opts),
+      inner: Some(inner),
+      pending: None,
+      partial: Vec::new(),
+      line: Line::default(),
+      buf: String::new(),
+    }
+  }
+
+  fn push(&mut self, c: char) {
+    if self.opts.whole_line() {
+      if let Some(spaced) = self.line.push(c, self.opts) {
+        self.buf.push_str(&spaced);
+      }
+      return;
+    }
+    if let Some(pre) = self.pending.replace(c) {
+      self.emit(pre, Some(c));
+    }
+  }
+
+  fn emit(&mut self, c: char, next: Option<char>) {
+    if !self.line.skips() && self.spacer.push(c, next) {
+      self.buf.push(' ');
+    }
+    self.buf.push(c);
+    self.line.track(c);
+    if c == '\n' {
+      self.spacer = Spacer::new(self.opts);
+    }
+  }
+
+  fn drain(&mut self) -> io::Result<()> {
+    if let Some(inner) = self.inner.as_mut() {
+      inner.write_all(self.buf.as_bytes())?;
+    }
+    self.buf.clear();
+    Ok(())
+  }
+
+  fn end(&mut self) -> io::Result<()> {
+    if !self.partial.is_empty() {
+      self.partial.clear();
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "stream did not end on a char boundary",
+      ));
+    }
+    if let Some(c) = self.pending.take() {
+      self.emit(c, None);
+    }
+    if self.opts.whole_line() {
+      let spaced = self.line.finish(self.opts);
+      self.buf.push_str(&spaced);
+    }
+    self.drain()?;
+    match self.inner.as_mut() {
+      Some(inner) => inner.flush(),
+      None => Ok(()),
+    }
+  }
+
+  /// The inner writer, holding what is spaced so far.
+  pub fn get_ref(&self) -> &W {
+    self.inner.as_ref().unwrap()
+  }
+
+  /// Writes the held back char and returns the inner writer.
+  pub fn finish(mut self) -> io::Result<W> {
+    self.end()?;
+    Ok(self.inner.take().unwrap())
+  }
+}
+
+impl<W: Write> Write for AddSpaceWriter<'_, W> {
+  fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+    let mut data = std::mem::take(&mut self.partial);
+    data.extend_from_slice(bytes);
+    let valid = match std::str::from_utf8(&data) {
+      Ok(s) => s.len(),
+      Err(e) if e.error_len().is_none() => e.valid_up_to(),
+      Err(e) => {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, e));
+      }
+    };
+    let (txt, rest) = data.split_at(valid);
+    for c in String::from_utf8_lossy(txt).chars() {
+      self.push(c);
+    }
+    self.partial = rest.to_vec();
+    self.drain()?;
+    Ok(bytes.len())
+  }
+
+  /// Flushes what is decided, the held back char waits for the next write or `finish`.
+  fn flush(&mut self) -> io::Result<()> {
+    self.drain()?;
+    match self.inner.as_mut() {
+      Some(inner) => inner.flush(),
+      None => Ok(()),
+    }
+  }
+}
+
+impl<W: Write> Drop for AddSpaceWriter<'_, W> {
+  fn drop(&mut self) {
+    if self.inner.is_some() {
+      let _ = self.end();
+    }
+  }
+}
+
+/// Streams `reader` into `writer` through an [`AddSpaceWriter`].
+pub fn add_space_io(reader: impl Read, writer: impl Write, opts: &Options) -> io::Result<()> {
+  let mut reader = reader;
+  let mut writer = AddSpaceWriter::new(writer, opts);
+  io::copy(&mut reader, &mut writer)?;
+  writer.finish()?;
+  Ok(())
+}
@@ -0,0 +1,151 @@
+use std::{ops::Range, path::Path};
+
+use crate::{Edit, Options, html::entity, project::Project};
+
+/// Subtitle formats understood by [`crate::Mode::Subtitle`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "conf", derive(serde::Deserialize))]
+#[cfg_attr(feature = "conf", serde(rename_all = "lowercase"))]
+pub enum Subtitle {
+  /// SubRip
+  Srt,
+  /// WebVTT
+  #[cfg_attr(feature = "conf", serde(alias = "webvtt"))]
+  Vtt,
+  /// Advanced SubStation Alpha and SubStation Alpha
+  #[cfg_attr(feature = "conf", serde(alias = "ssa"))]
+  Ass,
+}
+
+impl Subtitle {
+  pub const EXTENSIONS: &[(&str, Subtitle)] = &[
+    ("srt", Subtitle::Srt),
+    ("vtt", Subtitle::Vtt),
+    ("ass", Subtitle::Ass),
+    ("ssa", Subtitle::Ass),
+  ];
+
+  pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+    let ext = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+    Self::EXTENSIONS
+      .iter()
+      .find(|(e, _)| *e == ext)
+      .map(|(_, sub)| *sub)
+  }
+}
+
+/// Lines of `src` with their byte range, line terminators left out.
+pub(crate) fn lines(src: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+  let mut start = 0;
+  src.split_inclusive('\n').map(move |line| {
+    let range = start..start + line.trim_end_matches(['\n', '\r']).len();
+    start += line.len();
+    range
+  })
+}
+
+/// Markup or escape at the start of cue text.
+enum Token {
+  /// `<b>`, `{\i1}`, invisible
+  Tag { open: bool },
+  /// `\N`
+  Break,
+  /// `&amp;`, `\h`
+  Char(char),
+}
+
+fn token(rest: &str, ass: bool) -> Option<(Token, usize)> {
+  let tag = |close: char, open: bool| rest.find(close).map(|end| (Token::Tag { open }, end + 1));
+  match rest.as_bytes() {
+    [b'<', next, ..] if !ass && (next.is_ascii_alphanumeric() || *next == b'/') => {
+      tag('>', *next != b'/')
+    }
+    [b'{', ..] if ass => tag('}', true),
+    [b'{', b'\\', ..] => tag('}', true),
+    [b'\\', b'N' | b'n', ..] if ass => Some((Token::Break, 2)),
+    [b'\\', b'h', ..] if ass => Some((Token::Char('\u{a0}'), 2)),
+    [b'&', ..] if !ass => entity(rest).map(|(c, len)| (Token::Char(c), len)),
+    _ => None,
+  }
+}
+
+/// Cue text at `range` into `project`: tags like `<b>` and `{\i1}` are invisible markup, in ASS
+/// `\N` breaks the line and `\h` is a hard space.
+fn cue(project: &mut Project, src: &str, range: Range<usize>, ass: bool) {
+  let mut start = range.start;
+  let mut i = start;
+  while i < range.end {
+    let rest = &src[i..range.end];
+    let Some((token, len)) = token(rest, ass) else {
+      i += rest.chars().next().map_or(1, char::len_utf8);
+      continue;
+    };
+    project.text(src, start..i);
+    match token {
+      Token::Tag { open } => {
+        if open {
+          project.anchor(i);
+        }
+      }
+      Token::Break => project.brk(),
+      Token::Char(c) => project.escaped(c, i..i + len),
+    }
+    i += len;
+    start = i;
+  }
+  project.text(src, start..range.end);
+  project.brk();
+}
+
+/// SubRip and WebVTT: the lines after a timing line up to the next blank one are cue text.
+/// Indices, cue identifiers, the header and `NOTE` / `STYLE` blocks have no timing line.
+fn cues(src: &str, project: &mut Project) {
+  let mut text = false;
+  for line in lines(src) {
+    let s = src[line.clone()].trim();
+    if s.is_empty() {
+      text = false;
+    } else if text {
+      cue(project, src, line, false);
+    } else if s.contains("-->") {
+      text = true;
+    }
+  }
+}
+
+/// ASS / SSA: the last field of `Dialogue` lines in `[Events]`, it may hold commas.
+fn events(src: &str, project: &mut Project) {
+  let mut events = false;
+  // Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+  let mut fields = 10;
+  for line in lines(src) {
+    let s = src[line.clone()].trim_start_matches('\u{feff}');
+    if s.starts_with('[') {
+      events = s.trim_end().eq_ignore_ascii_case("[events]");
+      continue;
+    }
+    if !events {
+      continue;
+    }
+    let Some((key, value)) = s.split_once(':') else {
+      continue;
+    };
+    if key.eq_ignore_ascii_case("format") {
+      fields = value.split(',').count().max(2);
+    } else if key.eq_ignore_ascii_case("dialogue") {
+      let start = line.end - value.len();
+      if let Some((i, _)) = value.match_indices(',').nth(fields - 2) {
+        cue(project, src, start + i + 1..line.end, true);
+      }
+    }
+  }
+}
+
+pub(crate) fn subtitle_edits(src: &str, sub: Subtitle, opts: &Options) -> Vec<Edit> {
+  let mut project = Project::default();
+  match sub {
+    Subtitle::Srt | Subtitle::Vtt => cues(src, &mut project),
+    Subtitle::Ass => events(src, &mut project),
+  }
+  project.edits(src, opts)
+}
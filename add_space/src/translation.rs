@@ -0,0 +1,205 @@
+use std::{ops::Range, path::Path};
+
+use crate::{
+  Edit, Options, Placeholder,
+  data::{Style, escape, string},
+  html::{Tag, close, entity},
+  project::Project,
+  subtitle::lines,
+};
+
+/// Translation file formats understood by [`crate::Mode::Translation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "conf", derive(serde::Deserialize))]
+#[cfg_attr(feature = "conf", serde(rename_all = "lowercase"))]
+pub enum Translation {
+  /// gettext `.po` and `.pot`
+  Po,
+  /// XLIFF 1.2 and 2.x
+  #[cfg_attr(feature = "conf", serde(alias = "xlf"))]
+  Xliff,
+}
+
+impl Translation {
+  pub const EXTENSIONS: &[(&str, Translation)] = &[
+    ("po", Translation::Po),
+    ("pot", Translation::Po),
+    ("xliff", Translation::Xliff),
+    ("xlf", Translation::Xliff),
+  ];
+
+  pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+    let ext = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+    Self::EXTENSIONS
+      .iter()
+      .find(|(e, _)| *e == ext)
+      .map(|(_, t)| *t)
+  }
+}
+
+/// How special chars are written in translated text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Escapes {
+  /// `\n`, `\"`, in PO strings
+  Backslash,
+  /// `&amp;`, in XML
+  Entity,
+  /// CDATA sections
+  Raw,
+}
+
+/// Translated text at `range` into `project`, `%%` and escapes are spaced as the char they stand
+/// for.
+fn target(project: &mut Project, src: &str, range: Range<usize>, escapes: Escapes) {
+  let b = src.as_bytes();
+  let mut start = range.start;
+  let mut i = start;
+  while i < range.end {
+    // tokens start with ASCII bytes, so `i` is on a char boundary when one is found
+    let rest = || &src[i..range.end];
+    let token = match b[i] {
+      b'%' if rest().starts_with("%%") => Some(('%', 2)),
+      b'\\' if escapes == Escapes::Backslash => escape(rest()),
+      b'&' if escapes == Escapes::Entity => entity(rest()),
+      _ => None,
+    };
+    let Some((c, len)) = token else {
+      i += 1;
+      continue;
+    };
+    project.text(src, start..i);
+    project.escaped(c, i..i + len);
+    i += len;
+    start = i;
+  }
+  project.text(src, start..range.end);
+}
+
+/// Field of a PO entry a line belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+  Msgid,
+  Msgstr,
+  Other,
+}
+
+/// gettext: `msgstr` and `msgstr[n]`, their continuation lines joined into one run. The header,
+/// the entry with an empty `msgid`, and obsolete `#~` entries are kept.
+fn po(src: &str, project: &mut Project) {
+  let b = src.as_bytes();
+  let mut field = Field::Other;
+  let mut context = false;
+  let mut header = false;
+  for line in lines(src) {
+    let s = src[line.clone()].trim_start();
+    let at = line.end - s.len();
+    let keyword = s.split([' ', '\t', '"']).next().unwrap_or_default();
+    if !keyword.is_empty() {
+      if field == Field::Msgstr {
+        project.brk();
+      }
+      field = Field::Other;
+      match keyword {
+        "msgctxt" => context = true,
+        "msgid" => {
+          field = Field::Msgid;
+          header = !context;
+          context = false;
+        }
+        _ if keyword.starts_with("msgstr") && !header => field = Field::Msgstr,
+        _ => {}
+      }
+    }
+    let Some(quote) = s.find('"') else {
+      continue;
+    };
+    let value = string(b, at + quote, Style::Escaped);
+    let value = value.start..value.end.min(line.end);
+    match field {
+      Field::Msgid if !value.is_empty() => header = false,
+      Field::Msgstr => target(project, src, value, Escapes::Backslash),
+      _ => {}
+    }
+  }
+}
+
+/// Inline elements holding native code rather than text: the begin and end of a paired code, an
+/// isolated one, and a placeholder.
+const NATIVE: &[&str] = &["bpt", "ept", "it", "ph"];
+
+/// XLIFF: the content of `<target>` elements, those of `<alt-trans>` suggestions left out. `<x/>`
+/// and `<ph>` placeholders are opaque, paired codes like `<g>` and `<bpt>` are invisible markup.
+fn xliff(src: &str, project: &mut Project) {
+  let mut target_of = false;
+  // depth of `<alt-trans>`
+  let mut alt = 0usize;
+  let mut i = 0;
+  let mut start = 0;
+  while let Some(lt) = src[i..].find('<') {
+    let lt = i + lt;
+    let rest = &src[lt..];
+    if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+      let end = cdata.find("]]>").map_or(src.len(), |e| lt + 9 + e);
+      if target_of {
+        target(project, src, start..lt, Escapes::Entity);
+        target(project, src, lt + 9..end, Escapes::Raw);
+      }
+      i = (end + 3).min(src.len());
+      start = i;
+      continue;
+    }
+    let (len, tag) = if rest.starts_with("<!--") {
+      (rest.find("-->").map_or(rest.len(), |e| e + 3), None)
+    } else if rest.starts_with("<!") || rest.starts_with("<?") {
+      (rest.find('>').map_or(rest.len(), |e| e + 1), None)
+    } else if let Some(tag) = Tag::parse(rest) {
+      (tag.len, Some(tag))
+    } else {
+      i = lt + 1;
+      continue;
+    };
+    if target_of {
+      target(project, src, start..lt, Escapes::Entity);
+    }
+    i = lt + len;
+    start = i;
+    let Some(tag) = tag else {
+      continue;
+    };
+    let empty = rest[..len].ends_with("/>");
+    // `xliff:target` and the like
+    let name = tag.name.rsplit(':').next().unwrap_or_default();
+    match name {
+      "alt-trans" if tag.end => alt = alt.saturating_sub(1),
+      "alt-trans" if !empty => alt += 1,
+      "target" if alt == 0 => {
+        target_of = !tag.end && !empty;
+        project.brk();
+      }
+      _ if !target_of || tag.end => {}
+      _ => {
+        if NATIVE.contains(&name) && !empty {
+          let end = close(src, i, &tag.name);
+          i = Tag::parse(&src[end..]).map_or(end, |t| end + t.len);
+          start = i;
+        }
+        if matches!(name, "x" | "ph") {
+          project.atom(lt..i);
+        } else if name != "ept" && name != "ex" && name != "ec" {
+          project.anchor(lt);
+        }
+      }
+    }
+  }
+}
+
+pub(crate) fn translation_edits(src: &str, t: Translation, opts: &Options) -> Vec<Edit> {
+  // printf conversions like `%s` are kept whatever the options
+  let opts = &opts.clone().placeholder(Placeholder::Printf);
+  let mut project = Project::default();
+  match t {
+    Translation::Po => po(src, &mut project),
+    Translation::Xliff => xliff(src, &mut project),
+  }
+  project.edits(src, opts)
+}
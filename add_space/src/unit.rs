@@ -0,0 +1,105 @@
+use crate::{Options, Rule, State, project::Fix, remove::is_fullwidth_punctuation};
+
+/// Units recognized after a number, extended with [`Options::unit`].
+#[rustfmt::skip]
+pub const UNITS: &[&str] = &[
+  // data
+  "B", "KB", "MB", "GB", "TB", "PB", "KiB", "MiB", "GiB", "TiB", "bps", "Kbps", "Mbps", "Gbps",
+  // length, mass
+  "nm", "μm", "mm", "cm", "m", "km", "mg", "g", "kg", "mL",
+  // time, frequency
+  "ns", "μs", "ms", "s", "min", "h", "Hz", "kHz", "MHz", "GHz",
+  // screen, power
+  "px", "pt", "dpi", "fps", "kW", "mAh",
+];
+
+/// Units that always stick to the number, whatever [`Options::unit_space`] says.
+pub const ATTACHED_UNITS: &[&str] = &["%", "‰", "°C", "°F", "°", "℃", "℉"];
+
+/// Length in chars of the longest of `units` at the start of `chars`, ending on a word boundary
+/// unless it is a symbol.
+fn unit_len<'u>(chars: &[char], units: impl IntoIterator<Item = &'u str>) -> Option<usize> {
+  units
+    .into_iter()
+    .filter(|unit| {
+      let n = unit.chars().count();
+      chars.len() >= n
+        && unit.chars().eq(chars[..n].iter().copied())
+        && (!unit.ends_with(|c: char| c.is_alphanumeric())
+          || chars
+            .get(n)
+            .is_none_or(|c| !c.is_ascii_alphanumeric() && *c != '_'))
+    })
+    .map(|unit| unit.chars().count())
+    .max()
+}
+
+fn is_word(c: char) -> bool {
+  c.is_ascii_alphanumeric() || c == '_' || c == '.'
+}
+
+/// Spaces added or removed between numbers and units of a run, `chars` are the run's chars after
+/// width fixes.
+pub(crate) fn unit_fixes(chars: &[char], opts: &Options) -> Vec<Fix> {
+  let mut r = Vec::new();
+  let Some(unit_space) = opts.unit_space else {
+    return r;
+  };
+  let mut i = 0;
+  while i < chars.len() {
+    if !chars[i].is_ascii_digit() || (i > 0 && is_word(chars[i - 1])) {
+      i += 1;
+      continue;
+    }
+    let number = i;
+    // 1,024.5
+    while i < chars.len()
+      && (chars[i].is_ascii_digit()
+        || (matches!(chars[i], '.' | ',') && chars.get(i + 1).is_some_and(char::is_ascii_digit)))
+    {
+      i += 1;
+    }
+    let spaces = chars[i..].iter().take_while(|&&c| c == ' ').count();
+    let start = i + spaces;
+    let rest = &chars[start..];
+
+    let (len, attached) = match unit_len(rest, ATTACHED_UNITS.iter().copied()) {
+      Some(len) => (len, true),
+      None => match unit_len(rest, opts.units.iter().map(String::as_str)) {
+        Some(len) => (len, false),
+        None => {
+          i = start;
+          continue;
+        }
+      },
+    };
+
+    // the quantity is spaced against CJK as one word, `5 km` and `25%` alike, never against
+    // full-width punctuation `remove_space` would take the space from again
+    let cjk = |c: char, before: bool| {
+      let rule = opts.rule_of(c);
+      opts.state(c) == State::Char
+        && !is_fullwidth_punctuation(c)
+        && if before {
+          rule.before_word
+        } else {
+          rule.after_word
+        }
+    };
+    if number > 0 && cjk(chars[number - 1], true) {
+      r.push(Fix::Space(number, Rule::CjkWord));
+    }
+    if spaces > 0 && (attached || !unit_space) {
+      r.push(Fix::Delete(i..start, Rule::Unit));
+    } else if spaces == 0 && !attached && unit_space {
+      r.push(Fix::Space(start, Rule::Unit));
+    }
+
+    let end = start + len;
+    if chars.get(end).is_some_and(|&c| cjk(c, false)) {
+      r.push(Fix::Space(end, Rule::WordCjk));
+    }
+    i = end;
+  }
+  r
+}
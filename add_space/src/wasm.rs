@@ -0,0 +1,126 @@
+//! JavaScript API, see `wasm.sh` for the npm package.
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::{Edit, Mode, conf::Raw};
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS: &str = r#"
+export type Protect = "url" | "email" | "mention" | "hashtag" | "semver" | "path";
+
+export type Placeholder = "mustache" | "shell" | "icu" | "printf";
+
+export interface Conf {
+  scripts?: string[];
+  script_rules?: Record<
+    string,
+    { before_word?: boolean; after_word?: boolean; fullwidth_punctuation?: boolean }
+  >;
+  fullwidth_digit?: boolean;
+  punctuation?: string;
+  emoji?: boolean;
+  space_after_punctuation?: boolean;
+  space_after_period?: boolean;
+  fullwidth_punctuation?: boolean;
+  halfwidth_punctuation?: boolean;
+  halfwidth_alphanumeric?: boolean;
+  unit_space?: boolean;
+  units?: string[];
+  strings?: boolean;
+  html_attributes?: string[];
+  keys?: string[];
+  skip_keys?: string[];
+  placeholders?: Placeholder[];
+  placeholder_space?: boolean;
+  protect?: Protect[];
+  protect_regex?: string[];
+  remove_space?: boolean;
+}
+
+export interface Edit {
+  start: number;
+  end: number;
+  text: string;
+  rule: string;
+  message: string;
+}
+"#;
+
+/// An [`Edit`] with offsets counted in UTF-16 code units, like JavaScript strings.
+#[derive(Serialize)]
+struct JsEdit {
+  start: usize,
+  end: usize,
+  text: String,
+  /// [`crate::Rule`] variant, `CjkWord`
+  rule: String,
+  message: String,
+}
+
+fn js_edits(txt: &str, edits: Vec<Edit>) -> Vec<JsEdit> {
+  let mut byte = 0;
+  let mut units = 0;
+  let mut count = |to: usize| {
+    units += txt[byte..to].encode_utf16().count();
+    byte = to;
+    units
+  };
+  edits
+    .into_iter()
+    .map(|e| JsEdit {
+      start: count(e.range.start),
+      end: count(e.range.end),
+      rule: format!("{:?}", e.rule),
+      message: e.rule.to_string(),
+      text: e.text,
+    })
+    .collect()
+}
+
+/// `text` (the default), `markdown`, `html`, a [`crate::Lang`], a [`crate::Subtitle`], a
+/// [`crate::Data`] or a [`crate::Translation`], see `Mode::from_name`.
+fn mode(name: Option<String>) -> crate::Result<Mode> {
+  name.map_or(Ok(Mode::Text), |name| Mode::from_name(&name))
+}
+
+/// Spacing options, from an object with the keys of `.add_space.toml`:
+/// `new Options({ fullwidth_punctuation: true, protect_regex: ["\\{\\w+\\}"] })`.
+#[wasm_bindgen]
+pub struct Options(crate::Options);
+
+#[wasm_bindgen]
+impl Options {
+  #[wasm_bindgen(constructor)]
+  pub fn new(
+    #[wasm_bindgen(unchecked_param_type = "Conf | undefined")] conf: JsValue,
+  ) -> Result<Options, JsError> {
+    if conf.is_undefined() || conf.is_null() {
+      return Ok(Self(crate::Options::default()));
+    }
+    let mut raw: Raw = serde_wasm_bindgen::from_value(conf)?;
+    Ok(Self(raw.options()?))
+  }
+
+  /// Formats `txt` read as `mode`, plain text by default.
+  pub fn format(&self, txt: &str, mode: Option<String>) -> Result<String, JsError> {
+    Ok(self::mode(mode)?.format(txt, &self.0))
+  }
+
+  /// Changes [`Options::format`] makes, sorted: `{ start, end, text, rule, message }`.
+  #[wasm_bindgen(unchecked_return_type = "Edit[]")]
+  pub fn edits(&self, txt: &str, mode: Option<String>) -> Result<JsValue, JsError> {
+    let edits = self::mode(mode)?.edits(txt, &self.0);
+    Ok(serde_wasm_bindgen::to_value(&js_edits(txt, edits))?)
+  }
+}
+
+#[wasm_bindgen(js_name = addSpace)]
+pub fn add_space(txt: &str) -> String {
+  crate::add_space(txt)
+}
+
+#[wasm_bindgen]
+pub fn normalize(txt: &str) -> String {
+  crate::normalize(txt)
+}
@@ -0,0 +1,128 @@
+use crate::{Options, Rule, State};
+
+/// Half-width punctuation and its full-width form.
+const PAIRS: [(char, char); 8] = [
+  (',', '，'),
+  ('.', '。'),
+  (';', '；'),
+  (':', '：'),
+  ('!', '！'),
+  ('?', '？'),
+  ('(', '（'),
+  (')', '）'),
+];
+
+fn to_full(c: char) -> Option<char> {
+  PAIRS.iter().find(|(h, _)| *h == c).map(|(_, f)| *f)
+}
+
+fn to_half(c: char) -> Option<char> {
+  PAIRS.iter().find(|(_, f)| *f == c).map(|(h, _)| *h)
+}
+
+/// Sentence punctuation in either width, and a line break, ending a clause.
+fn is_mark(c: char) -> bool {
+  !matches!(c, '(' | '（' | ')' | '）')
+    && (to_full(c).is_some() || to_half(c).is_some() || c == '、' || c == '\n')
+}
+
+/// `Ａ`-`Ｚ`, `ａ`-`ｚ` and `０`-`９` as ASCII.
+fn alphanumeric_to_half(c: char) -> Option<char> {
+  match c {
+    'Ａ'..='Ｚ' | 'ａ'..='ｚ' | '０'..='９' => char::from_u32(c as u32 - 0xfee0),
+    _ => None,
+  }
+}
+
+/// Width fixes for a run of chars: index -> replacement.
+///
+/// Full-width letters and digits are converted first, so punctuation and spacing see the ASCII
+/// forms. Sentence punctuation follows its neighbours, `中文,中文` -> `中文，中文`, and turns
+/// half-width only in a Latin clause, `word，word` -> `word,word` but `我喜欢Python。` is kept. A pair of parentheses follows the text outside it, or its content when alone.
+pub(crate) fn normalize(chars: &[char], opts: &Options) -> Vec<Option<(char, Rule)>> {
+  let mut r = vec![None; chars.len()];
+  let mut chars = chars.to_vec();
+  if opts.halfwidth_alphanumeric {
+    for (c, fix) in chars.iter_mut().zip(&mut r) {
+      if let Some(h) = alphanumeric_to_half(*c) {
+        *fix = Some((h, Rule::HalfwidthAlphanumeric));
+        *c = h;
+      }
+    }
+  }
+  if !opts.fullwidth_punctuation && !opts.halfwidth_punctuation {
+    return r;
+  }
+  // CJK text taking full-width punctuation, not Korean or Thai
+  let cjk = |c: &char| opts.state(*c) == State::Char && opts.rule_of(*c).fullwidth_punctuation;
+  let full = |c| to_full(c).map(|f| (f, Rule::FullwidthPunctuation));
+  let half = |c| to_half(c).map(|h| (h, Rule::HalfwidthPunctuation));
+
+  let mut set = |chars: &mut [char], i: usize, fix: Option<(char, Rule)>| {
+    if let Some((c, _)) = fix {
+      r[i] = fix;
+      chars[i] = c;
+    }
+  };
+
+  // parentheses first, then sentence punctuation sees them and its left neighbour converted, so a
+  // second pass finds nothing left to do
+  let mut stack = Vec::new();
+  for i in 0..chars.len() {
+    match chars[i] {
+      '(' | '（' => stack.push(i),
+      ')' | '）' => {
+        if let Some(open) = stack.pop() {
+          // nearest non-space chars outside the pair
+          let outside = [
+            chars[..open].iter().rev().find(|c| !c.is_whitespace()),
+            chars[i + 1..].iter().find(|c| !c.is_whitespace()),
+          ];
+          let cjk_inside = chars[open + 1..i].iter().any(cjk);
+          let cjk_outside = if outside.iter().all(Option::is_none) {
+            cjk_inside
+          } else {
+            outside.into_iter().flatten().any(cjk)
+          };
+          let fix = if cjk_outside && opts.fullwidth_punctuation {
+            full
+          } else if !cjk_outside && !cjk_inside && opts.halfwidth_punctuation {
+            half
+          } else {
+            continue;
+          };
+          for j in [open, i] {
+            let fix = fix(chars[j]);
+            set(&mut chars, j, fix);
+          }
+        }
+      }
+      _ => {}
+    }
+  }
+  for i in 0..chars.len() {
+    let c = chars[i];
+    if matches!(c, '(' | '（' | ')' | '）') {
+      continue;
+    }
+    let pre = i.checked_sub(1).map(|i| &chars[i]);
+    let next = chars.get(i + 1);
+    // the clause around the mark, up to the neighbouring marks
+    let ends = |c: &&char| is_mark(**c);
+    let before = chars[..i].iter().rev().take_while(|c| !ends(c));
+    let after = chars[i + 1..].iter().take_while(|c| !ends(c));
+    let fix = if opts.fullwidth_punctuation && pre.is_some_and(cjk) && next.is_none_or(cjk) {
+      full(c)
+    } else if opts.halfwidth_punctuation
+      && pre.is_some_and(|&c| opts.state(c) == State::Letter)
+      && !before.clone().any(cjk)
+      && !after.clone().any(cjk)
+    {
+      half(c)
+    } else {
+      None
+    };
+    set(&mut chars, i, fix);
+  }
+  r
+}
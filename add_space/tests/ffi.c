@@ -0,0 +1,28 @@
+#include <assert.h>
+#include <string.h>
+
+#include "add_space.h"
+
+static void check(char *got, const char *want) {
+  assert(got && strcmp(got, want) == 0);
+  add_space_free(got);
+}
+
+int main(void) {
+  check(add_space("中文English中文"), "中文 English 中文");
+  check(add_space_normalize("中 文 内 容 ， 和English"), "中文内容，和 English");
+
+  char *err = NULL;
+  AddSpaceOptions *opts = add_space_options_new("fullwidth_punctuation = true", &err);
+  assert(opts && !err);
+  check(add_space_format(opts, "你好,世界", NULL), "你好，世界");
+  check(add_space_format(opts, "// 注释comment\nlet s = \"中文a\";", "rust"),
+        "// 注释 comment\nlet s = \"中文a\";");
+  assert(!add_space_format(opts, "a", "cobol"));
+  add_space_options_free(opts);
+
+  assert(!add_space_options_new("scripts = [\"Nope\"]", &err));
+  check(err, "unknown script: Nope");
+  assert(!add_space(NULL));
+  return 0;
+}
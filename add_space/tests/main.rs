@@ -1,4 +1,10 @@
-use add_space::add_space;
+use add_space::{
+  AddSpaceWriter, Data, Lang, Mode, Options, Placeholder, Protect, Regex, Rule, Script, ScriptRule,
+  Subtitle, Translation, add_space, add_space_chars, add_space_io, add_space_md, add_space_with,
+  apply_edits, edits, is_md, normalize,
+};
+#[cfg(feature = "cli")]
+use add_space::{CONF_FILE, Conf};
 use aok::{OK, Void};
 use tracing::info;
 
@@ -125,7 +131,1257 @@ fn test() -> Void {
     info!("{}", add);
     assert_eq!(add, exp);
   }
-  // info!("{}", add_space(r#"测试<p>图片描述</p>一下"#));
+  OK
+}
+
+#[test]
+fn test_data() -> Void {
+  let opts = Options::default().placeholder_space(true);
+  // keys, comments and ICU placeholders are kept, escapes are spaced as what they stand for
+  let json = "{\n  // 注释comment\n  \"标题Title\": \"欢迎Welcome\",\n  \"list\": [\"你好World\", 1, \
+    {\"name\": \"小明Ming\"}],\n  \"count\": \"共{count}个files{n, plural, one {# 个item}}\",\n  \
+    \"esc\": \"\\u4e2dBC\\n第二Line\"\n}\n";
+  assert_eq!(
+    Mode::Data(Data::Json).format(json, &opts),
+    json
+      .replace("迎Welcome", "迎 Welcome")
+      .replace("好World", "好 World")
+      .replace("明Ming", "明 Ming")
+      .replace("共{count}个files{", "共 {count} 个 files{")
+      .replace("dBC", "d BC")
+      .replace("二Line", "二 Line")
+  );
+  let only = opts.clone().keys(["list"]).skip_keys(["list.name"]);
+  assert_eq!(
+    Mode::Data(Data::Json).format(json, &only),
+    json.replace("好World", "好 World")
+  );
+
+  let yaml = "# 注释comment\nhome:\n  title: 欢迎Welcome  # 注释comment\n  tag: 中文#tag\n  \
+    quoted: '它''s中文OK'\n  list:\n  - 第一item\n  - desc: |\n      第一行line\n";
+  assert_eq!(
+    Mode::Data(Data::Yaml).format(yaml, &opts),
+    yaml
+      .replace("迎Welcome", "迎 Welcome")
+      .replace("s中文OK", "s 中文 OK")
+      .replace("一item", "一 item")
+      .replace("行line", "行 line")
+  );
+
+  let toml = "# 注释comment\ntitle = \"欢迎Welcome\" # 注释comment\n[home]\nlit = '中文lit'\n\
+    multi = \"\"\"\n第一行line\"\"\"\ninline = { a = \"中文ab\", b.c = \"中文cd\" }\n";
+  assert_eq!(
+    Mode::Data(Data::Toml).format(toml, &opts.clone().skip_keys(["inline.b"])),
+    toml
+      .replace("迎Welcome", "迎 Welcome")
+      .replace("文lit", "文 lit")
+      .replace("行line", "行 line")
+      .replace("文ab", "文 ab")
+  );
+  assert_eq!(Mode::from_path("zh.yml"), Mode::Data(Data::Yaml));
+  OK
+}
+
+#[test]
+fn test_translation() -> Void {
+  let opts = Options::default().placeholder_space(true);
+  // the header, source strings and obsolete entries are kept, continuation lines are one string
+  let po = "msgid \"\"\nmsgstr \"\"\n\"Project-Id-Version: 中文Project\\n\"\n\n\
+    #, c-format\nmsgid \"Hello %s world\"\nmsgstr \"你好%s世界Hello\"\n\n\
+    msgctxt \"菜单Menu\"\nmsgid \"Open\"\nmsgstr \"\"\n\"打开文件\"\n\"File共%1$d个items\"\n\n\
+    msgid \"One\"\nmsgid_plural \"Many\"\nmsgstr[0] \"一个%(name)s名字\"\nmsgstr[1] \"多个\\n第二行Line\"\n\n\
+    #~ msgid \"Old\"\n#~ msgstr \"旧的Old\"\n";
+  assert_eq!(
+    Mode::Translation(Translation::Po).format(po, &opts),
+    po.replace("你好%s世界Hello", "你好 %s 世界 Hello")
+      .replace("\"File共%1$d个items", "\" File 共 %1$d 个 items")
+      .replace("一个%(name)s名字", "一个 %(name)s 名字")
+      .replace("二行Line", "二行 Line")
+  );
+
+  // only targets, `<x/>` and `<ph>` are placeholders, `<g>` and `<bpt>` invisible markup
+  let xliff = "<?xml version=\"1.0\"?>\n<xliff version=\"1.2\"><file><body>\n\
+    <trans-unit id=\"1\"><source>Hello World</source><target>你好World世界</target>\n\
+    <alt-trans><target>建议Suggestion</target></alt-trans></trans-unit>\n\
+    <trans-unit id=\"2\"><target>点击<x id=\"1\"/>按钮，<g id=\"2\">加粗Bold</g>文本</target>\n\
+    <target>共<ph id=\"1\">%d</ph>个<bpt id=\"2\">&lt;b&gt;</bpt>粗体Bold<ept id=\"2\">&lt;/b&gt;</ept>\
+    </target><note>注释Note</note></trans-unit>\n</body></file></xliff>\n";
+  assert_eq!(
+    Mode::Translation(Translation::Xliff).format(xliff, &opts),
+    xliff
+      .replace("你好World世界", "你好 World 世界")
+      .replace("点击<x id=\"1\"/>按钮", "点击 <x id=\"1\"/> 按钮")
+      .replace("加粗Bold</g>文本", "加粗 Bold</g> 文本")
+      .replace("共<ph id=\"1\">%d</ph>个", "共 <ph id=\"1\">%d</ph> 个")
+      .replace("粗体Bold", "粗体 Bold")
+  );
+  assert_eq!(
+    Mode::from_path("zh.xlf"),
+    Mode::Translation(Translation::Xliff)
+  );
+  OK
+}
+
+#[test]
+fn test_subtitle() -> Void {
+  let opts = Options::default();
+  let srt = "\u{feff}1\r\n00:00:01,000 --> 00:00:02,000\r\n{\\an8}这是<i>English</i>字幕\r\n\
+    第二行Line 2\r\n\r\n2\r\n00:00:03,000 --> 00:00:04,000\r\n<font color=\"#fff\">中文Text</font>\r\n";
+  assert_eq!(
+    Mode::Subtitle(Subtitle::Srt).format(srt, &opts),
+    "\u{feff}1\r\n00:00:01,000 --> 00:00:02,000\r\n{\\an8}这是 <i>English</i> 字幕\r\n\
+    第二行 Line 2\r\n\r\n2\r\n00:00:03,000 --> 00:00:04,000\r\n<font color=\"#fff\">中文 Text</font>\r\n"
+  );
+
+  // the header, STYLE and NOTE blocks and cue identifiers are kept
+  let vtt = "WEBVTT - 中文Title\n\nSTYLE\n::cue { color: red } /* 中文a */\n\nNOTE 中文Note\n\n\
+    intro中文ID\n00:00.000 --> 00:02.000 align:start\n\
+    <v 小明>你好World</v>\n<c.yellow>中文</c>English&amp;中文\n";
+  assert_eq!(
+    Mode::Subtitle(Subtitle::Vtt).format(vtt, &opts),
+    "WEBVTT - 中文Title\n\nSTYLE\n::cue { color: red } /* 中文a */\n\nNOTE 中文Note\n\n\
+    intro中文ID\n00:00.000 --> 00:02.000 align:start\n\
+    <v 小明>你好 World</v>\n<c.yellow>中文</c> English&amp; 中文\n"
+  );
+
+  // only the Text field of Dialogue lines, `\N` breaks the line
+  let ass = "[Script Info]\nTitle: 中文Title\n\n\
+    [V4+ Styles]\nFormat: Name, Fontname\nStyle: Default,微软雅黑Arial\n\n\
+    [Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+    Dialogue: 0,0:00:01.00,0:00:02.00,Default,小明A,0,0,0,,{\\i1}你好World{\\i0}，再见Bye\\N第二行Line\n\
+    Comment: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,注释Comment\n";
+  assert_eq!(
+    Mode::Subtitle(Subtitle::Ass).format(ass, &opts),
+    ass
+      .replace("你好World", "你好 World")
+      .replace("再见Bye", "再见 Bye")
+      .replace("二行Line", "二行 Line")
+  );
+  assert_eq!(Mode::from_path("a.SSA"), Mode::Subtitle(Subtitle::Ass));
+  OK
+}
+
+#[test]
+fn test_html() -> Void {
+  let opts = Options::default();
+  for (txt, exp) in [
+    (r#"测试<p>图片描述</p>一下"#, r#"测试<p>图片描述</p>一下"#),
+    (
+      r#"测试<img alt="图片描述">一下"#,
+      r#"测试<img alt="图片描述">一下"#,
+    ),
+    (
+      r#"<p>中文<b>English</b>中文</p>"#,
+      r#"<p>中文 <b>English</b> 中文</p>"#,
+    ),
+    (
+      "<P>中文<A HREF='x'>link</A>中文</P><PRE>中文a</PRE>",
+      "<P>中文 <A HREF='x'>link</A> 中文</P><PRE>中文a</PRE>",
+    ),
+    // code is a word, pre, script and style are kept
+    (
+      "<p>用<code>ls -la</code>命令</p><pre>中文English</pre><p>中文English</p>",
+      "<p>用 <code>ls -la</code> 命令</p><pre>中文English</pre><p>中文 English</p>",
+    ),
+    (
+      "<script>var s = '中文English<p>';</script><style>p::after{content:'中文a'}</style>中文English",
+      "<script>var s = '中文English<p>';</script><style>p::after{content:'中文a'}</style>中文 English",
+    ),
+    (
+      "<!DOCTYPE html>\n<html><head><title>标题Title</title></head>\n<!-- 注释comment -->\n",
+      "<!DOCTYPE html>\n<html><head><title>标题 Title</title></head>\n<!-- 注释comment -->\n",
+    ),
+    // entities are spaced as the char they stand for
+    ("中文&nbsp;English&amp;中文", "中文&nbsp;English&amp; 中文"),
+    (
+      r#"<img alt="图片description" src="中文a.png">"#,
+      r#"<img alt="图片description" src="中文a.png">"#,
+    ),
+    ("a < b 中文English", "a < b 中文 English"),
+  ] {
+    assert_eq!(Mode::Html.format(txt, &opts), exp);
+  }
+
+  let opts = opts.html_attributes(["alt", "title"]);
+  assert_eq!(
+    Mode::Html.format(
+      r#"<img alt="图片description" title='标题Title' src="中文a.png">中文English"#,
+      &opts
+    ),
+    r#"<img alt="图片 description" title='标题 Title' src="中文a.png">中文 English"#
+  );
+  assert_eq!(Mode::from_path("index.HTML"), Mode::Html);
+  OK
+}
+
+#[test]
+fn test_md() -> Void {
+  for (txt, exp) in [
+    (
+      "翻译能够完美保持`Markdown`的格式。",
+      "翻译能够完美保持 `Markdown` 的格式。",
+    ),
+    ("使用`中文code`命令", "使用 `中文code` 命令"),
+    ("中文[English](url)中文", "中文 [English](url) 中文"),
+    (
+      "点击[链接](http://a.com/中文abc)查看",
+      "点击[链接](http://a.com/中文abc)查看",
+    ),
+    ("中文**English**中文", "中文 **English** 中文"),
+    ("# 标题Title\n\n正文text\n", "# 标题 Title\n\n正文 text\n"),
+    (
+      "---\ntitle: 中文abc\n---\n\n中文abc\n",
+      "---\ntitle: 中文abc\n---\n\n中文 abc\n",
+    ),
+    (
+      "说明text\n\n```rust\nlet s = \"中文abc\";\n```\n",
+      "说明 text\n\n```rust\nlet s = \"中文abc\";\n```\n",
+    ),
+    (
+      "测试<span title=\"中文abc\">中文</span>一下\r\n",
+      "测试<span title=\"中文abc\">中文</span>一下\r\n",
+    ),
+    ("- 列表item\n- 另一个item", "- 列表 item\n- 另一个 item"),
+  ] {
+    let add = add_space_md(txt);
+    info!("{}", add);
+    assert_eq!(add, exp);
+  }
+  assert!(is_md("README.md"));
+  assert!(!is_md("main.rs"));
+
+  OK
+}
+
+#[test]
+fn test_options() -> Void {
+  let default = Options::default();
+  for txt in [
+    "中文English中文",
+    "当你凝视着bug，bug也凝视着你",
+    "中文数字１２３和123",
+    "Hello,world!Yes.No",
+  ] {
+    assert_eq!(add_space_with(txt, &default), add_space(txt));
+  }
+
+  for (opts, txt, exp) in [
+    (
+      Options::default().scripts([Script::Han]),
+      "中文abcカタカナabc",
+      "中文 abcカタカナabc",
+    ),
+    (
+      Options::default().fullwidth_digit(false),
+      "数字１２３和abc",
+      "数字 １２３ 和 abc",
+    ),
+    (
+      Options::default().space_after_punctuation(false),
+      "你好,world",
+      "你好,world",
+    ),
+    (Options::default(), "你好,world", "你好, world"),
+    (
+      Options::default().space_after_period(false),
+      "中文.Hello",
+      "中文.Hello",
+    ),
+    (Options::default(), "中文.Hello", "中文. Hello"),
+    (
+      Options::default().punctuation("，"),
+      "中文!abc",
+      "中文 ! abc",
+    ),
+  ] {
+    let add = add_space_with(txt, &opts);
+    info!("{}", add);
+    assert_eq!(add, exp);
+  }
+
+  OK
+}
+
+#[test]
+fn test_punctuation_width() -> Void {
+  let full = Options::default().fullwidth_punctuation(true);
+  let half = Options::default().halfwidth_punctuation(true);
+  let both = full.clone().halfwidth_punctuation(true);
+  for (opts, txt, exp) in [
+    (&full, "中文,中文;中文:中文!", "中文，中文；中文：中文！"),
+    (&full, "你好?", "你好？"),
+    // the line end closes the sentence
+    (&full, "你好,世界.\n第二行", "你好，世界。\n第二行"),
+    (&full, "中文(English)中文", "中文（English）中文"),
+    (&full, "(中文)", "（中文）"),
+    (&full, "中文,English", "中文, English"),
+    (&full, "版本1.0,时间12:30", "版本 1.0,时间 12:30"),
+    (&full, "中文...", "中文..."),
+    (&full, "Hello，world", "Hello，world"),
+    (&half, "Hello，world！", "Hello, world!"),
+    (&half, "Hello。World", "Hello. World"),
+    (&half, "Some text（note）here", "Some text(note)here"),
+    (&half, "（note）", "(note)"),
+    (&half, "English（中文）", "English（中文）"),
+    (&half, "abc，中文", "abc，中文"),
+    (&half, "中文,中文", "中文,中文"),
+    // a Latin word ends a Chinese clause
+    (&half, "我喜欢Python。", "我喜欢 Python。"),
+    (&half, "这是Rust。这是Go。", "这是 Rust。这是 Go。"),
+    (
+      &half,
+      "我用Python，Go和Rust写代码",
+      "我用 Python，Go 和 Rust 写代码",
+    ),
+    (
+      &both,
+      "中文,中文 and English，too",
+      "中文，中文 and English，too",
+    ),
+    (&both, "中文(abc)和text（x）", "中文（abc）和 text(x)"),
+  ] {
+    assert_eq!(add_space_with(txt, opts), exp, "{txt}");
+    assert_eq!(Mode::Text.format(txt, opts), exp, "{txt}");
+    assert_eq!(add_space_chars(txt.chars(), opts).collect::<String>(), exp);
+  }
+
+  let txt = "中文,中文\r\nHello，world\n";
+  let mut out = Vec::new();
+  add_space_io(txt.as_bytes(), &mut out, &both)?;
+  assert_eq!(String::from_utf8(out)?, "中文，中文\r\nHello, world\n");
+
+  let txt = "中文,中文";
+  let e = edits(txt, &full);
+  assert_eq!(e.len(), 1);
+  assert_eq!(e[0].range, 6..7);
+  assert_eq!(e[0].char_range, 2..3);
+  assert_eq!(e[0].text, "，");
+  assert_eq!(e[0].rule, Rule::FullwidthPunctuation);
+
+  assert_eq!(
+    Mode::Markdown.format("中文,**加粗**(`code`)中文\n", &full),
+    "中文，**加粗**（`code`）中文\n"
+  );
+  assert_eq!(add_space("中文,中文"), "中文,中文");
+  OK
+}
+
+#[test]
+fn test_halfwidth_alphanumeric() -> Void {
+  let opts = Options::default().halfwidth_alphanumeric(true);
+  for (txt, exp) in [
+    ("中文ＡＢＣ１２３中文", "中文 ABC123 中文"),
+    ("数字１２３和abc", "数字 123 和 abc"),
+    ("ＡｐｐｌｅＩＤ", "AppleID"),
+    (
+      "使用Ｒｕｓｔ写的ａｄｄ＿ｓｐａｃｅ",
+      "使用 Rust 写的 add＿space",
+    ),
+    ("中文 English", "中文 English"),
+  ] {
+    assert_eq!(add_space_with(txt, &opts), exp, "{txt}");
+    assert_eq!(Mode::Text.format(txt, &opts), exp, "{txt}");
+  }
+  // left alone by default
+  assert_eq!(add_space("AＡBＢCＣ"), "AＡBＢCＣ");
+
+  let both = opts.clone().halfwidth_punctuation(true);
+  assert_eq!(
+    add_space_with("Ｈｅｌｌｏ，ｗｏｒｌｄ", &both),
+    "Hello, world"
+  );
+
+  let e = edits("中ＡＢ", &opts);
+  assert_eq!(
+    e.iter()
+      .map(|e| (e.range.clone(), e.text.as_str(), e.rule))
+      .collect::<Vec<_>>(),
+    [
+      (3..3, " ", Rule::CjkWord),
+      (3..6, "A", Rule::HalfwidthAlphanumeric),
+      (6..9, "B", Rule::HalfwidthAlphanumeric),
+    ]
+  );
+  OK
+}
+
+#[test]
+fn test_scripts() -> Void {
+  let opts = Options::default();
+  for (lang, txt, exp) in [
+    ("zh", "使用Rust编写", "使用 Rust 编写"),
+    ("zh", "扩展𠀀区abc", "扩展𠀀区 abc"),
+    ("zh", "注音ㄅㄆㄇabc", "注音ㄅㄆㄇ abc"),
+    (
+      "ja",
+      "日本語のRustドキュメント",
+      "日本語の Rust ドキュメント",
+    ),
+    ("ja", "サーバーAPIを呼ぶ", "サーバー API を呼ぶ"),
+    ("ja", "ｶﾀｶﾅabc", "ｶﾀｶﾅ abc"),
+    ("ko", "iPhone을 샀다", "iPhone을 샀다"),
+    ("ko", "한국어English", "한국어English"),
+    ("ko", "Rust로 작성", "Rust로 작성"),
+    ("yi", "ꆈꌠabc", "ꆈꌠ abc"),
+    ("th", "ภาษาไทยabc", "ภาษาไทย abc"),
+  ] {
+    assert_eq!(add_space_with(txt, &opts), exp, "{lang}: {txt}");
+  }
+  assert_eq!(add_space("サーバーAPI"), "サーバー API");
+  // common brackets whose script extensions list Han are no CJK chars
+  assert_eq!(add_space("中文【English】"), "中文【English】");
+  assert_eq!(add_space("看《Rust编程》一书"), "看《Rust 编程》一书");
+  assert_eq!(add_space("〈abc〉"), "〈abc〉");
+  assert_eq!(add_space("１【E"), "１【E");
+  // a mention and a path are protected spans, spaced like a word
+  assert_eq!(add_space("ア@E"), "ア @E");
+  assert_eq!(add_space("１./E"), "１ ./E");
+  let plain = Options::default().protect([]);
+  assert_eq!(add_space_with("ア@E", &plain), "ア@E");
+  assert_eq!(add_space_with("１./E", &plain), "１./E");
+  assert_eq!(
+    add_space_with("【10GB】", &Options::default().unit_space(true)),
+    "【10 GB】"
+  );
+
+  // Korean keeps half-width punctuation, Chinese and Japanese take full-width
+  let full = Options::default().fullwidth_punctuation(true);
+  assert_eq!(add_space_with("안녕,세상", &full), "안녕,세상");
+  assert_eq!(add_space_with("中文,中文", &full), "中文，中文");
+  assert_eq!(add_space_with("日本語!", &full), "日本語！");
+  assert_eq!(add_space_with("ภาษาไทย,ภาษาไทย", &full), "ภาษาไทย,ภาษาไทย");
+  let half = Options::default().halfwidth_punctuation(true);
+  assert_eq!(add_space_with("Rust，한국어", &half), "Rust,한국어");
+
+  let spaced = Options::default().script_rule(Script::Hangul, ScriptRule::CJK);
+  assert_eq!(add_space_with("한국어English", &spaced), "한국어 English");
+  let tight = Options::default().script_rule(
+    Script::Han,
+    ScriptRule {
+      after_word: false,
+      ..ScriptRule::CJK
+    },
+  );
+  assert_eq!(
+    add_space_with("中文English中文", &tight),
+    "中文 English中文"
+  );
+  OK
+}
+
+#[test]
+fn test_unit() -> Void {
+  let tight = Options::default().unit_space(false);
+  let spaced = Options::default().unit_space(true);
+  for (txt, tight_exp, spaced_exp) in [
+    ("硬盘10GB", "硬盘 10GB", "硬盘 10 GB"),
+    ("硬盘10 GB", "硬盘 10GB", "硬盘 10 GB"),
+    ("跑了5 km和3km", "跑了 5km 和 3km", "跑了 5 km 和 3 km"),
+    ("增长25 %的收入", "增长 25% 的收入", "增长 25% 的收入"),
+    ("增长25%的收入", "增长 25% 的收入", "增长 25% 的收入"),
+    ("气温30 °C", "气温 30°C", "气温 30°C"),
+    ("气温30℃以上", "气温 30℃ 以上", "气温 30℃ 以上"),
+    ("容量1024.5MB", "容量 1024.5MB", "容量 1024.5 MB"),
+    // a thousands separator is part of the number
+    ("1,024MB", "1,024MB", "1,024 MB"),
+    ("共1,024个", "共 1,024 个", "共 1,024 个"),
+    ("容量1,024.5MB", "容量 1,024.5MB", "容量 1,024.5 MB"),
+    (
+      "延迟100ms,带宽1Gbps",
+      "延迟 100ms,带宽 1Gbps",
+      "延迟 100 ms,带宽 1 Gbps",
+    ),
+    // not units: word boundary, letters before the number
+    ("5 men and 3 mice", "5 men and 3 mice", "5 men and 3 mice"),
+    ("H2O and MP3s", "H2O and MP3s", "H2O and MP3s"),
+    ("OAuth 2.0鉴权", "OAuth 2.0 鉴权", "OAuth 2.0 鉴权"),
+  ] {
+    assert_eq!(add_space_with(txt, &tight), tight_exp, "{txt}");
+    assert_eq!(add_space_with(txt, &spaced), spaced_exp, "{txt}");
+    assert_eq!(Mode::Text.format(txt, &spaced), spaced_exp, "{txt}");
+  }
+  // off by default
+  assert_eq!(add_space("硬盘10 GB和25 %"), "硬盘 10 GB 和 25 %");
+
+  let custom = Options::default().unit_space(true).unit("rpm");
+  assert_eq!(add_space_with("转速7200rpm", &custom), "转速 7200 rpm");
+  assert_eq!(add_space_with("转速7200rpm", &spaced), "转速 7200rpm");
+
+  let e = edits("10 GB", &tight);
+  assert_eq!(e.len(), 1);
+  assert_eq!((e[0].range.clone(), e[0].text.as_str()), (2..3, ""));
+  assert_eq!(e[0].rule, Rule::Unit);
+  assert_eq!(
+    Mode::Markdown.format("容量10**GB**\n", &spaced),
+    "容量 10 **GB**\n"
+  );
+  OK
+}
+
+#[test]
+fn test_protect() -> Void {
+  for (txt, exp) in [
+    (
+      "联系John.Smith@example.com获取",
+      "联系 John.Smith@example.com 获取",
+    ),
+    (
+      "访问https://example.com/a?b=1,Hello获取",
+      "访问 https://example.com/a?b=1,Hello 获取",
+    ),
+    (
+      "详见https://en.wikipedia.org/wiki/Rust_(language)。",
+      "详见 https://en.wikipedia.org/wiki/Rust_(language)。",
+    ),
+    (
+      "(见https://example.com)中文",
+      "(见https://example.com) 中文",
+    ),
+    ("关注@rustlang和#rust话题", "关注 @rustlang 和 #rust 话题"),
+    ("升级到v2.0.0-beta.1发布", "升级到 v2.0.0-beta.1 发布"),
+    ("配置在~/.config/App.Toml里", "配置在 ~/.config/App.Toml 里"),
+    // not protected: part of a word, an address
+    ("C#和a@b", "C#和 a@b"),
+    ("地址10.0.0.1可用", "地址 10.0.0.1 可用"),
+  ] {
+    assert_eq!(add_space(txt), exp, "{txt}");
+    assert_eq!(
+      Mode::Markdown.format(txt, &Options::default()),
+      exp,
+      "{txt}"
+    );
+  }
+  let unprotected = Options::default().protect([]);
+  assert_eq!(
+    add_space_with("联系John.Smith@example.com", &unprotected),
+    "联系 John. Smith@example.com"
+  );
+
+  // never changed inside
+  let units = Options::default().unit_space(true);
+  assert_eq!(
+    add_space_with("下载到/tmp/10GB.iso", &units),
+    "下载到 /tmp/10GB.iso"
+  );
+  assert_eq!(
+    add_space_with("下载到/tmp/10GB.iso", &units.clone().protect([])),
+    "下载到 /tmp/10 GB.iso"
+  );
+
+  let custom = Options::default()
+    .protect([Protect::Url])
+    .protect_regex(Regex::new("[a-z]+,[A-Z][a-z]+")?);
+  assert_eq!(add_space_with("见foo,Bar配置", &custom), "见 foo,Bar 配置");
+  assert_eq!(add_space("见foo,Bar配置"), "见 foo, Bar 配置");
+  assert_eq!(
+    add_space_chars("关注@rustlang和#rust话题".chars(), &Options::default()).collect::<String>(),
+    "关注 @rustlang 和 #rust 话题"
+  );
+  OK
+}
+
+#[test]
+fn test_placeholder() -> Void {
+  let spaced = Options::default().placeholder_space(true);
+  for (txt, exp) in [
+    ("共有{count}个文件", "共有 {count} 个文件"),
+    ("欢迎%s登录，第%1$d页", "欢迎 %s 登录，第 %1$d 页"),
+    ("你好{{ user.name }}欢迎", "你好 {{ user.name }} 欢迎"),
+    ("路径${HOME}目录", "路径 ${HOME} 目录"),
+    (
+      "共{n, plural, one {# 个item} other {# 个items}}条",
+      "共 {n, plural, one {# 个item} other {# 个items}} 条",
+    ),
+    // not placeholders
+    ("打折20%off", "打折 20%off"),
+    ("集合{a,b}中", "集合{a,b} 中"),
+    ("{张三}说", "{张三}说"),
+  ] {
+    assert_eq!(add_space_with(txt, &spaced), exp, "{txt}");
+  }
+  // left alone by default, spaced as without placeholders elsewhere
+  let plain = Options::default().placeholders([]);
+  for txt in [
+    "共有{count}个文件",
+    "共有 {count} 个文件",
+    "{张三}说",
+    "%@ア",
+  ] {
+    assert_eq!(add_space(txt), add_space_with(txt, &plain), "{txt}");
+  }
+  let tight = Options::default().placeholder_space(false);
+  assert_eq!(
+    add_space_with("共有 {count} 个文件，Hello {name}", &tight),
+    "共有{count}个文件，Hello {name}"
+  );
+  let e = edits("共有\u{3000}{count}个", &tight);
+  assert_eq!(e.len(), 1);
+  assert_eq!((e[0].range.clone(), e[0].text.as_str()), (6..9, ""));
+  let printf = spaced.clone().placeholders([Placeholder::Printf]);
+  assert_eq!(
+    add_space_with("欢迎%s登录{{name}}", &printf),
+    "欢迎 %s 登录{{name}}"
+  );
+  OK
+}
+
+#[test]
+fn test_ignore() -> Void {
+  use std::io::Write;
+
+  let opts = Options::default();
+  let txt = "中文abc\n<!-- add_space-ignore-next-line -->\n中文abc\n中文abc\n\
+<!-- add_space-ignore-start -->\n中文abc\n\n中文abc\n<!-- add_space-ignore-end -->\n中文abc\n";
+  let exp = "中文 abc\n<!-- add_space-ignore-next-line -->\n中文abc\n中文 abc\n\
+<!-- add_space-ignore-start -->\n中文abc\n\n中文abc\n<!-- add_space-ignore-end -->\n中文 abc\n";
+  assert_eq!(Mode::Text.format(txt, &opts), exp);
+  assert_eq!(Mode::Markdown.format(txt, &opts), exp);
+  assert_eq!(add_space(txt), exp);
+  assert_eq!(add_space_chars(txt.chars(), &opts).collect::<String>(), exp);
+  let mut w = AddSpaceWriter::new(Vec::new(), &opts);
+  for b in txt.as_bytes() {
+    w.write_all(&[*b])?;
+  }
+  assert_eq!(String::from_utf8(w.finish()?)?, exp);
+  #[cfg(feature = "cli")]
+  {
+    let out = cli(&["--no-config"], txt)?;
+    assert_eq!(String::from_utf8(out.stdout)?, exp);
+  }
+  // streams spacing char by char only keep the start of a line, enough for a long reason
+  let chars = Options::default().protect([]).placeholders([]);
+  let long = format!(
+    "# add_space-ignore-next-line {}\n中文abc\n",
+    "理由".repeat(500)
+  );
+  assert_eq!(
+    add_space_chars(long.chars(), &chars).collect::<String>(),
+    long
+  );
+  let mut w = AddSpaceWriter::new(Vec::new(), &chars);
+  w.write_all(long.as_bytes())?;
+  assert_eq!(String::from_utf8(w.finish()?)?, long);
+
+  let rust = "// 中文abc\n  // add_space-ignore-next-line: 错误示例\n// 中文abc\n";
+  assert_eq!(
+    Mode::Code(Lang::Rust).format(rust, &opts),
+    "// 中文 abc\n  // add_space-ignore-next-line: 错误示例\n// 中文abc\n"
+  );
+  let yaml = "# add_space-ignore-start\na: 中文abc\n# add_space-ignore-end\nb: 中文abc\n";
+  assert_eq!(
+    Mode::Data(Data::Yaml).format(yaml, &opts),
+    "# add_space-ignore-start\na: 中文abc\n# add_space-ignore-end\nb: 中文 abc\n"
+  );
+  // a mention is no directive
+  let md = "用`<!-- add_space-ignore-start -->`忽略\n中文abc\n";
+  assert_eq!(
+    Mode::Markdown.format(md, &opts),
+    "用 `<!-- add_space-ignore-start -->` 忽略\n中文 abc\n"
+  );
+  OK
+}
+
+#[test]
+fn test_remove_space() -> Void {
+  for (txt, exp) in [
+    ("中 文 内 容 ， 和English", "中文内容，和 English"),
+    ("中文 ， English ！", "中文，English！"),
+    ("「 引 用 」 和 （ 注 ）", "「引用」和（注）"),
+    ("这是 一个 iPhone 手机", "这是一个 iPhone 手机"),
+    ("中 文English", "中文 English"),
+    ("  中 文  ", "  中文  "),
+    ("中　文", "中文"),
+    // spaced scripts and Latin text keep their spaces
+    ("한국어 문장", "한국어 문장"),
+    ("ภาษา ไทย", "ภาษา ไทย"),
+    ("a b , c", "a b , c"),
+  ] {
+    assert_eq!(normalize(txt), exp, "{txt}");
+    assert_eq!(normalize(exp), exp, "{txt}");
+  }
+  // a round trip through add_space is canonical
+  let txt = "OAuth 2.0鉴权用户只能查询到通过OAuth 2.0鉴权创建的会议";
+  assert_eq!(normalize(add_space(txt)), add_space(txt));
+  // off by default
+  assert_eq!(add_space("中 文"), "中 文");
+
+  let opts = Options::default().remove_space(true);
+  let e = edits("中 文", &opts);
+  assert_eq!(e.len(), 1);
+  assert_eq!((e[0].range.clone(), e[0].text.as_str()), (3..4, ""));
+  assert_eq!(e[0].rule, Rule::ExtraSpace);
+  let unit = opts.clone().unit_space(true);
+  assert_eq!(add_space_with("【1km", &unit), "【1 km");
+  assert_eq!(add_space_with("，1km", &unit), "，1 km");
+  assert_eq!(
+    Mode::Markdown.format("**中 文** 内 容 `code` 。\n", &opts),
+    "**中文**内容 `code`。\n"
+  );
+  // spaces an emphasis needs to open or close stay
+  for txt in [
+    "**「中文」** 内容\n",
+    "内容 **「中文」**\n",
+    "_中文_ 内容\n",
+  ] {
+    assert_eq!(Mode::Markdown.format(txt, &opts), txt);
+  }
+  assert_eq!(
+    add_space_chars("中 文\n内 容".chars(), &opts).collect::<String>(),
+    "中文\n内容"
+  );
+  OK
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_conf() -> Void {
+  let root = std::env::temp_dir().join(format!("add_space_conf_{}", std::process::id()));
+  let sub = root.join("doc/sub");
+  std::fs::create_dir_all(&sub)?;
+  std::fs::write(
+    root.join(CONF_FILE),
+    r#"
+scripts = ["Han", "Hira"]
+space_after_punctuation = false
+exclude = ["vendor/**"]
+
+strings = true
+unit_space = true
+units = ["rpm"]
+protect = ["url", "email"]
+protect_regex = ["[A-Z]+-[0-9]+"]
+remove_space = true
+
+[script_rules.Hangul]
+before_word = true
+
+[mode]
+txt = "markdown"
+h = "typescript"
+"#,
+  )?;
+  let file = sub.join("a.txt");
+  std::fs::write(&file, "中文abc")?;
+
+  let conf = Conf::find(&file)?.unwrap();
+  assert_eq!(conf.options.scripts, [Script::Han, Script::Hiragana]);
+  assert!(!conf.options.space_after_punctuation);
+  assert_eq!(conf.mode(&file), Mode::Markdown);
+  assert_eq!(conf.mode(sub.join("a.md")), Mode::Markdown);
+  assert_eq!(conf.mode(sub.join("a.rs")), Mode::Code(Lang::Rust));
+  assert_eq!(conf.mode(sub.join("a.h")), Mode::Code(Lang::JavaScript));
+  assert_eq!(conf.mode(sub.join("a.rst")), Mode::Text);
+  assert!(conf.options.strings);
+  assert!(conf.options.remove_space);
+  assert_eq!(conf.options.unit_space, Some(true));
+  assert!(conf.options.units.iter().any(|u| u == "rpm"));
+  assert!(conf.options.units.iter().any(|u| u == "GB"));
+  assert_eq!(
+    conf.options.protect,
+    [
+      Protect::Url,
+      Protect::Email,
+      Protect::Regex(Regex::new("[A-Z]+-[0-9]+")?)
+    ]
+  );
+  assert!(conf.options.script_rules.contains(&(
+    Script::Hangul,
+    ScriptRule {
+      before_word: true,
+      ..ScriptRule::KOREAN
+    }
+  )));
+  assert!(conf.is_included(&file));
+  assert!(!conf.is_included(root.join("vendor/x/a.md")));
+
+  // Cargo.toml metadata table, nearer than .add_space.toml
+  std::fs::write(
+    root.join("doc/Cargo.toml"),
+    "[package]\nname = \"x\"\n\n[package.metadata.add_space]\nfullwidth_digit = false\ninclude = [\"sub/*.md\"]\n",
+  )?;
+  let conf = Conf::find(&file)?.unwrap();
+  assert!(!conf.options.fullwidth_digit);
+  assert!(!conf.is_included(&file));
+  assert!(conf.is_included(sub.join("a.md")));
+
+  std::fs::write(root.join("doc/Cargo.toml"), "[package]\nname = \"x\"\n")?;
+  assert!(Conf::find(&file)?.unwrap().options.scripts.len() == 2);
+
+  std::fs::write(root.join(CONF_FILE), "scripts = [\"Klingon\"]\n")?;
+  assert!(Conf::find(&file).is_err());
+  std::fs::write(root.join(CONF_FILE), "protect_regex = [\"(\"]\n")?;
+  assert!(Conf::find(&file).is_err());
+
+  std::fs::remove_dir_all(&root)?;
+  OK
+}
+
+#[cfg(feature = "cli")]
+fn cli(args: &[&str], stdin: &str) -> std::io::Result<std::process::Output> {
+  use std::{
+    io::Write,
+    process::{Command, Stdio},
+  };
+  let mut child = Command::new(env!("CARGO_BIN_EXE_add_space"))
+    .args(args)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .spawn()?;
+  child.stdin.take().unwrap().write_all(stdin.as_bytes())?;
+  child.wait_with_output()
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_check() -> Void {
+  let out = cli(&["--check", "--no-config", "--color", "never"], "中文 abc")?;
+  assert!(out.status.success());
+  assert!(out.stdout.is_empty());
+
+  let out = cli(&["--check", "--no-config", "--color", "never"], "中文abc")?;
+  assert_eq!(out.status.code(), Some(1));
+  let diff = String::from_utf8(out.stdout)?;
+  info!("{}", diff);
+  assert!(diff.contains("-中文abc\n"));
+  assert!(diff.contains("+中文 abc\n"));
+  OK
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_walk() -> Void {
+  let root = std::env::temp_dir().join(format!("add_space_walk_{}", std::process::id()));
+  std::fs::create_dir_all(root.join("a/b"))?;
+  for (file, txt) in [
+    ("a/x.md", "中文abc\n"),
+    ("a/b/y.md", "中文 abc\n"),
+    ("a/b/z.rs", "// 中文abc\n"),
+    ("a/b/w.srt", "1\n00:00:01,000 --> 00:00:02,000\n中文abc\n"),
+    ("a/b/v.bin", "中文abc\n"),
+  ] {
+    std::fs::write(root.join(file), txt)?;
+  }
+  let dir = root.join("a");
+  let dir = dir.to_str().unwrap();
+
+  let out = cli(&["--check", "--no-config", "--color", "never", dir], "")?;
+  assert_eq!(out.status.code(), Some(1));
+  let diff = String::from_utf8(out.stdout)?;
+  assert!(diff.contains("x.md"));
+  // every extension with a mode is walked, unknown ones are not
+  assert!(diff.contains("z.rs"));
+  assert!(diff.contains("w.srt"));
+  assert!(!diff.contains("v.bin"));
+  let out = cli(&["--check", "--no-config", "--ext", "md", dir], "")?;
+  assert!(!String::from_utf8(out.stdout)?.contains("z.rs"));
+
+  let unchanged = root.join("a/b/y.md");
+  let mtime = std::fs::metadata(&unchanged)?.modified()?;
+  let out = cli(&["--write", "--no-config", "-j", "2", dir], "")?;
+  assert!(out.status.success());
+  assert_eq!(std::fs::metadata(&unchanged)?.modified()?, mtime);
+  assert_eq!(std::fs::read_to_string(root.join("a/x.md"))?, "中文 abc\n");
+  assert_eq!(
+    std::fs::read_to_string(root.join("a/b/z.rs"))?,
+    "// 中文 abc\n"
+  );
+
+  // the configuration's include globs and mode table pick files too
+  std::fs::write(
+    root.join("a/.add_space.toml"),
+    "include = [\"**/*.bin\", \"**/*.note\"]\n[mode]\nnote = \"markdown\"\n",
+  )?;
+  std::fs::write(root.join("a/u.note"), "中文abc\n`中文abc`\n")?;
+  let out = cli(&["--check", "--color", "never", dir], "")?;
+  let diff = String::from_utf8(out.stdout)?;
+  assert!(diff.contains("v.bin"));
+  // as Markdown, the code span is kept
+  assert!(diff.contains("u.note"));
+  assert!(!diff.contains("`中文 abc`"));
+
+  let glob = format!("{dir}/**/*.bin");
+  let out = cli(&["--check", "--no-config", &glob], "")?;
+  assert_eq!(out.status.code(), Some(1));
+  // relative, with a leading `./` or without
+  for glob in ["./a/**/*.bin", "a/**/*.bin"] {
+    let out = std::process::Command::new(env!("CARGO_BIN_EXE_add_space"))
+      .args(["--check", "--no-config", "--color", "never", glob])
+      .current_dir(&root)
+      .output()?;
+    assert!(String::from_utf8(out.stdout)?.contains("v.bin"), "{glob}");
+  }
+
+  std::fs::remove_dir_all(&root)?;
+  OK
+}
+
+#[test]
+fn test_line_ending() -> Void {
+  let opts = Options::default();
+  for (mode, txt, exp) in [
+    (
+      Mode::Text,
+      "中文abc\r\n中文abc\r\n",
+      "中文 abc\r\n中文 abc\r\n",
+    ),
+    (Mode::Text, "中文abc\n\n中文abc", "中文 abc\n\n中文 abc"),
+    (Mode::Text, "中文abc\n", "中文 abc\n"),
+    (Mode::Text, "\u{feff}中文abc\r\n", "\u{feff}中文 abc\r\n"),
+    (Mode::Text, "", ""),
+    (
+      Mode::Markdown,
+      "\u{feff}# 中文abc\r\n\r\n",
+      "\u{feff}# 中文 abc\r\n\r\n",
+    ),
+  ] {
+    assert_eq!(mode.format(txt, &opts), exp);
+  }
+  OK
+}
+
+#[test]
+fn test_stream() -> Void {
+  use std::io::Write;
+
+  let opts = Options::default();
+  for txt in [
+    "中文English中文\r\n当你凝视着bug，bug也凝视着你\n",
+    "\u{feff}a中\n[中文abc\n中文abc]",
+    "abc\\\n中文",
+    "",
+    "中",
+  ] {
+    let exp = Mode::Text.format(txt, &opts);
+    assert_eq!(add_space_chars(txt.chars(), &opts).collect::<String>(), exp);
+
+    // one byte per write, UTF-8 sequences arrive split
+    let mut w = AddSpaceWriter::new(Vec::new(), &opts);
+    for b in txt.as_bytes() {
+      w.write_all(&[*b])?;
+    }
+    assert_eq!(String::from_utf8(w.finish()?)?, exp);
+
+    let mut out = Vec::new();
+    add_space_io(txt.as_bytes(), &mut out, &opts)?;
+    assert_eq!(String::from_utf8(out)?, exp);
+  }
+
+  let mut w = AddSpaceWriter::new(Vec::new(), &opts);
+  assert!(w.write_all(&[0xff, b'a']).is_err());
+  let mut w = AddSpaceWriter::new(Vec::new(), &opts);
+  w.write_all(&"中".as_bytes()[..2])?;
+  assert!(w.finish().is_err());
+
+  // the default options hold back a line, char spacing alone one char
+  let mut w = AddSpaceWriter::new(Vec::new(), &opts);
+  w.write_all("中文abc\n中文abc".as_bytes())?;
+  assert_eq!(w.get_ref().as_slice(), "中文 abc\n".as_bytes());
+  assert_eq!(String::from_utf8(w.finish()?)?, "中文 abc\n中文 abc");
+  let chars = Options::default().protect([]).placeholders([]);
+  let mut w = AddSpaceWriter::new(Vec::new(), &chars);
+  w.write_all("中文abc".as_bytes())?;
+  assert_eq!(w.get_ref().as_slice(), "中文 ab".as_bytes());
+  // spacing starts afresh on every line, whatever the options
+  let txt = "{\nmsgstr \"カＡＢ";
+  let exp = "{\nmsgstr \"カ ＡＢ";
+  assert_eq!(add_space_with(txt, &chars), exp);
+  assert_eq!(Mode::Text.format(txt, &chars), exp);
+  assert_eq!(
+    add_space_chars(txt.chars(), &chars).collect::<String>(),
+    exp
+  );
+
+  // a line without a newline is flushed in pieces cut after whitespace
+  let txt = "中文abc {n}个 ".repeat(20000);
+  let exp = Mode::Text.format(&txt, &opts);
+  let mut w = AddSpaceWriter::new(Vec::new(), &opts);
+  w.write_all(txt.as_bytes())?;
+  assert!(w.get_ref().len() > exp.len() / 2);
+  assert_eq!(String::from_utf8(w.finish()?)?, exp);
+  assert_eq!(add_space_chars(txt.chars(), &opts).collect::<String>(), exp);
+  let mut w = AddSpaceWriter::new(Vec::new(), &opts);
+  w.write_all("中".repeat(100000).as_bytes())?;
+  assert!(!w.get_ref().is_empty());
+  OK
+}
+
+#[test]
+fn test_edits() -> Void {
+  let opts = Options::default();
+  let txt = "中文English中文，你好,world\n第二行abc";
+  let edits = edits(txt, &opts);
+  let got: Vec<_> = edits
+    .iter()
+    .map(|e| {
+      (
+        e.range.clone(),
+        e.char_range.clone(),
+        e.text.as_str(),
+        e.rule,
+      )
+    })
+    .collect();
+  assert_eq!(
+    got,
+    [
+      (6..6, 2..2, " ", Rule::CjkWord),
+      (13..13, 9..9, " ", Rule::WordCjk),
+      (29..29, 15..15, " ", Rule::Punctuation),
+      (44..44, 24..24, " ", Rule::CjkWord),
+    ]
+  );
+  assert_eq!(apply_edits(txt, &edits), Mode::Text.format(txt, &opts));
+  // apply selectively
+  assert_eq!(
+    apply_edits(txt, &edits[..1]),
+    "中文 English中文，你好,world\n第二行abc"
+  );
+
+  let md = "中文`code`中文";
+  let edits = Mode::Markdown.edits(md, &opts);
+  assert_eq!(
+    edits.iter().map(|e| e.range.start).collect::<Vec<_>>(),
+    [6, 12]
+  );
+  assert_eq!(apply_edits(md, &edits), add_space_md(md));
+  OK
+}
+
+#[cfg(feature = "lsp")]
+#[test]
+fn test_lsp() -> Void {
+  use std::{
+    io::{BufRead, BufReader, Read, Write},
+    process::{Command, Stdio},
+  };
+
+  use serde_json::{Value, json};
+
+  let mut child = Command::new(env!("CARGO_BIN_EXE_add_space-lsp"))
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .spawn()?;
+  let mut stdin = child.stdin.take().unwrap();
+  let mut stdout = BufReader::new(child.stdout.take().unwrap());
+
+  let mut send = |msg: Value| -> std::io::Result<()> {
+    let body = msg.to_string();
+    write!(stdin, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    stdin.flush()
+  };
+  let mut recv = || -> aok::Result<Value> {
+    let mut len = 0;
+    loop {
+      let mut line = String::new();
+      stdout.read_line(&mut line)?;
+      let line = line.trim_end();
+      if line.is_empty() {
+        break;
+      }
+      if let Some(n) = line.strip_prefix("Content-Length: ") {
+        len = n.parse()?;
+      }
+    }
+    let mut body = vec![0; len];
+    stdout.read_exact(&mut body)?;
+    Ok(serde_json::from_slice(&body)?)
+  };
+
+  let uri = "untitled:doc";
+  send(json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"capabilities": {}}}))?;
+  let init = recv()?;
+  assert_eq!(
+    init["result"]["capabilities"]["documentFormattingProvider"],
+    true
+  );
+  send(json!({"jsonrpc": "2.0", "method": "initialized", "params": {}}))?;
+  send(
+    json!({"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+      "textDocument": {"uri": uri, "languageId": "plaintext", "version": 1, "text": "😀\n中文abc"}
+    }}),
+  )?;
+
+  let diag = recv()?;
+  assert_eq!(diag["method"], "textDocument/publishDiagnostics");
+  let diagnostics = diag["params"]["diagnostics"].as_array().unwrap();
+  assert_eq!(diagnostics.len(), 1);
+  assert_eq!(
+    diagnostics[0]["range"],
+    json!({"start": {"line": 1, "character": 1}, "end": {"line": 1, "character": 3}})
+  );
+  assert_eq!(diagnostics[0]["source"], "add_space");
+
+  send(
+    json!({"jsonrpc": "2.0", "id": 2, "method": "textDocument/formatting", "params": {
+      "textDocument": {"uri": uri}, "options": {"tabSize": 2, "insertSpaces": true}
+    }}),
+  )?;
+  assert_eq!(
+    recv()?["result"],
+    json!([{"range": {"start": {"line": 1, "character": 2}, "end": {"line": 1, "character": 2}}, "newText": " "}])
+  );
+
+  send(
+    json!({"jsonrpc": "2.0", "id": 3, "method": "textDocument/codeAction", "params": {
+      "textDocument": {"uri": uri},
+      "range": {"start": {"line": 1, "character": 0}, "end": {"line": 1, "character": 0}},
+      "context": {"diagnostics": []}
+    }}),
+  )?;
+  let actions = recv()?;
+  let kinds: Vec<_> = actions["result"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|a| a["kind"].as_str().unwrap())
+    .collect();
+  assert_eq!(kinds, ["source.fixAll"]);
+
+  // a broken configuration is shown and nothing is spaced, like the CLI failing
+  let root = std::env::temp_dir().join(format!("add_space_lsp_{}", std::process::id()));
+  std::fs::create_dir_all(&root)?;
+  std::fs::write(root.join(".add_space.toml"), "scripts = [\"Klingon\"]\n")?;
+  let uri = format!("file://{}/a.md", root.display());
+  send(
+    json!({"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+      "textDocument": {"uri": uri, "languageId": "markdown", "version": 1, "text": "中文abc"}
+    }}),
+  )?;
+  let msg = recv()?;
+  assert_eq!(msg["method"], "window/showMessage");
+  assert_eq!(msg["params"]["type"], 1);
+  assert!(
+    msg["params"]["message"]
+      .as_str()
+      .unwrap()
+      .contains("Klingon")
+  );
+  let diag = recv()?;
+  assert_eq!(diag["params"]["diagnostics"], json!([]));
+  std::fs::remove_dir_all(&root)?;
+
+  // so are bad params, the server keeps running
+  send(json!({"jsonrpc": "2.0", "method": "textDocument/didChange", "params": {}}))?;
+  let msg = recv()?;
+  assert_eq!(msg["method"], "window/showMessage");
+  assert_eq!(msg["params"]["type"], 1);
+
+  send(json!({"jsonrpc": "2.0", "id": 4, "method": "shutdown"}))?;
+  recv()?;
+  send(json!({"jsonrpc": "2.0", "method": "exit"}))?;
+  assert!(child.wait()?.success());
+  OK
+}
+
+#[test]
+fn test_code() -> Void {
+  let opts = Options::default();
+  let rust = r##"//! 模块doc注释
+/// 使用`add_space`函数处理text
+fn main() {
+  let s = "中文abc"; // 行尾comment注释
+  let c = '"'; let r = r#"中文"raw"#;
+  /* 块注释block /* 嵌套nested */ 结束end */
+  let lifetime: &'static str = "x"; // 还有comment
+}
+"##;
+  let exp = r##"//! 模块 doc 注释
+/// 使用 `add_space` 函数处理 text
+fn main() {
+  let s = "中文abc"; // 行尾 comment 注释
+  let c = '"'; let r = r#"中文"raw"#;
+  /* 块注释 block /* 嵌套 nested */ 结束 end */
+  let lifetime: &'static str = "x"; // 还有 comment
+}
+"##;
+  assert_eq!(Mode::Code(Lang::Rust).format(rust, &opts), exp);
+  assert_eq!(Mode::from_path("a.rs"), Mode::Code(Lang::Rust));
+  assert_eq!(
+    Mode::Code(Lang::Rust).format(rust, &opts.clone().strings(true)),
+    exp.replace("\"中文abc\"", "\"中文 abc\"")
+  );
+
+  let ts = "const a = x / 2; // 除以two\nconst re = /\"中文abc/; /** 正则regex */\nconst t = `模板${f(\"中文abc\")}text`;\nconst s = '中文abc';\n";
+  assert_eq!(
+    Mode::Code(Lang::JavaScript).format(ts, &opts),
+    ts.replace("除以two", "除以 two")
+      .replace("正则regex", "正则 regex")
+  );
+  assert_eq!(
+    Mode::Code(Lang::JavaScript).format(ts, &opts.clone().strings(true)),
+    "const a = x / 2; // 除以 two\nconst re = /\"中文abc/; /** 正则 regex */\nconst t = `模板${f(\"中文 abc\")}text`;\nconst s = '中文 abc';\n"
+  );
+
+  let py = "def f():\n    \"\"\"文档docstring\n    第二行line\"\"\"\n    x = \"中文abc\"  # 注释comment\n    y = '''多行\n中文abc'''\n";
+  assert_eq!(
+    Mode::Code(Lang::Python).format(py, &opts),
+    py.replace("文档docstring", "文档 docstring")
+      .replace("第二行line", "第二行 line")
+      .replace("注释comment", "注释 comment")
+  );
+
+  let go = "// 包package说明\npackage main\n\nvar r = '中'\nvar s = `原始raw字符串`\n";
+  assert_eq!(
+    Mode::Code(Lang::Go).format(go, &opts),
+    go.replace("包package说明", "包 package 说明")
+  );
+  assert_eq!(
+    Mode::Code(Lang::Go).format(go, &opts.clone().strings(true)),
+    "// 包 package 说明\npackage main\n\nvar r = '中'\nvar s = `原始 raw 字符串`\n"
+  );
+  OK
+}
+
+#[cfg(feature = "ffi")]
+#[test]
+fn test_ffi() -> Void {
+  use std::{
+    ffi::{CStr, CString, c_char},
+    ptr,
+  };
+
+  use add_space::ffi::*;
+
+  let take = |s: *mut c_char| {
+    assert!(!s.is_null());
+    let r = unsafe { CStr::from_ptr(s) }.to_str().unwrap().to_owned();
+    unsafe { add_space_free(s) };
+    r
+  };
+  let c = |s: &str| CString::new(s).unwrap();
+
+  assert_eq!(
+    take(unsafe { add_space(c("中文English中文").as_ptr()) }),
+    "中文 English 中文"
+  );
+  assert_eq!(
+    take(unsafe { add_space_normalize(c("中 文 ， 和English").as_ptr()) }),
+    "中文，和 English"
+  );
+
+  let mut err = ptr::null_mut();
+  let opts = unsafe { add_space_options_new(c("fullwidth_punctuation = true").as_ptr(), &mut err) };
+  assert!(!opts.is_null() && err.is_null());
+  assert_eq!(
+    take(unsafe { add_space_format(opts, c("你好,世界").as_ptr(), ptr::null()) }),
+    "你好，世界"
+  );
+  assert_eq!(
+    take(unsafe { add_space_format(opts, c("# 标题Title").as_ptr(), c("markdown").as_ptr()) }),
+    "# 标题 Title"
+  );
+  assert!(unsafe { add_space_format(opts, c("a").as_ptr(), c("cobol").as_ptr()) }.is_null());
+  unsafe { add_space_options_free(opts) };
 
+  // defaults without options
+  assert_eq!(
+    take(unsafe { add_space_format(ptr::null(), c("中文ab").as_ptr(), c("Text").as_ptr()) }),
+    "中文 ab"
+  );
+  assert!(unsafe { add_space_options_new(c("nope = 1").as_ptr(), &mut err) }.is_null());
+  assert!(take(err).contains("unknown field `nope`"));
+  assert!(unsafe { add_space(ptr::null()) }.is_null());
+  // not UTF-8
+  assert!(unsafe { add_space(c"\xff".as_ptr()) }.is_null());
   OK
 }
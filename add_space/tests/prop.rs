@@ -0,0 +1,155 @@
+use add_space::{
+  Data, Lang, Mode, Options, Subtitle, Translation, add_space, add_space_with, edits,
+};
+use proptest::prelude::*;
+
+/// Text mixing CJK, Latin words, numbers, units, punctuation and protected spans.
+fn txt() -> impl Strategy<Value = String> {
+  let token = prop_oneof![
+    "[中文字日本語かなカナ한국어ภาษา]{1,3}",
+    "[a-zA-Z]{1,5}",
+    "[0-9]{1,4}",
+    "[ ,.!?:;()\\[\\]{}`'\"%#@/~\\\\+-]",
+    "[，。！？：；（）「」、【】《》]",
+    prop::sample::select(vec![
+      " ",
+      "  ",
+      "\t",
+      "\n",
+      "\n\n",
+      "１２",
+      "ＡＢ",
+      "GB",
+      "km",
+      "℃",
+      "1.2.3",
+      "https://a.com/x,Y",
+      "a.B@c.com",
+      "@user",
+      "#tag",
+      "~/a/b",
+      "**",
+      "_",
+      "~~",
+      "[a](b)",
+      "<br>",
+      "<p>",
+      "</p>",
+      "<code>",
+      "</code>",
+      "<b>",
+      "&amp;",
+      "&nbsp;",
+      "# ",
+      "> ",
+      "- ",
+      "// ",
+      "/*",
+      "*/",
+      "#[",
+      "r#\"",
+      "\": \"",
+      "\n- ",
+      " = '",
+      "\\u4e2d",
+      "{n}",
+      "{{a}}",
+      "${x}",
+      "%s",
+      "{0, plural, one {# 个}}",
+      "\nmsgstr \"",
+      "%1$d",
+      "<target>",
+      "<x/>",
+      "\n00:00:01,000 --> 00:00:02,000\n",
+      "\n00:01.000 --> 00:02.000\n",
+      "\nDialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,",
+      "{\\i1}",
+      "\\N",
+      "\n# add_space-ignore-next-line\n",
+      "\n<!-- add_space-ignore-start -->\n",
+      "\n// add_space-ignore-end\n",
+    ])
+    .prop_map(String::from),
+  ];
+  prop::collection::vec(token, 0..24).prop_map(|tokens| tokens.concat())
+}
+
+fn presets() -> Vec<Options> {
+  vec![
+    Options::default(),
+    Options::default().protect([]),
+    Options::default()
+      .fullwidth_punctuation(true)
+      .halfwidth_punctuation(true)
+      .halfwidth_alphanumeric(true),
+    Options::default().unit_space(true),
+    Options::default().unit_space(false),
+    Options::default().remove_space(true),
+    Options::default().unit_space(true).remove_space(true),
+    Options::default().placeholder_space(true),
+    Options::default()
+      .placeholder_space(false)
+      .remove_space(true),
+  ]
+}
+
+/// Whether `sub` is `txt` with chars left out.
+fn is_subsequence(sub: &str, txt: &str) -> bool {
+  let mut chars = txt.chars();
+  sub.chars().all(|c| chars.any(|t| t == c))
+}
+
+proptest! {
+  #![proptest_config(ProptestConfig::with_cases(1024))]
+
+  #[test]
+  fn idempotent(txt in txt()) {
+    for (i, opts) in presets().iter().enumerate() {
+      let once = add_space_with(&txt, opts);
+      prop_assert_eq!(add_space_with(&once, opts), once.clone(), "preset {}", i);
+      for mode in [
+        Mode::Text,
+        Mode::Markdown,
+        Mode::Html,
+        Mode::Code(Lang::Rust),
+        Mode::Code(Lang::JavaScript),
+        Mode::Code(Lang::Python),
+        Mode::Code(Lang::Go),
+        Mode::Data(Data::Json),
+        Mode::Data(Data::Yaml),
+        Mode::Data(Data::Toml),
+        Mode::Translation(Translation::Po),
+        Mode::Translation(Translation::Xliff),
+        Mode::Subtitle(Subtitle::Srt),
+        Mode::Subtitle(Subtitle::Vtt),
+        Mode::Subtitle(Subtitle::Ass),
+      ] {
+        let once = mode.format(&txt, opts);
+        prop_assert_eq!(mode.format(&once, opts), once.clone(), "{:?} preset {}", mode, i);
+      }
+    }
+  }
+
+  #[test]
+  fn only_spaces_inserted(txt in txt()) {
+    let r = add_space(&txt);
+    prop_assert!(is_subsequence(&txt, &r));
+    prop_assert_eq!(r.chars().filter(|c| *c != ' ').collect::<String>(),
+      txt.chars().filter(|c| *c != ' ').collect::<String>());
+    for e in edits(&txt, &Options::default()) {
+      prop_assert!(e.range.is_empty() && e.text == " ", "{:?}", e);
+    }
+  }
+
+  #[test]
+  fn no_char_lost(txt in txt()) {
+    // replacements keep one char for one, removals only drop whitespace
+    for (i, opts) in presets().iter().enumerate() {
+      let r = add_space_with(&txt, opts);
+      let count = |s: &str| s.chars().filter(|c| !c.is_whitespace()).count();
+      prop_assert_eq!(count(&r), count(&txt), "preset {}", i);
+      prop_assert_eq!(r.lines().count(), txt.lines().count());
+    }
+  }
+}
@@ -0,0 +1,43 @@
+import { readFileSync } from "node:fs";
+import { expect, test } from "bun:test";
+import { addSpace, initSync, normalize, Options } from "../pkg/add_space.js";
+
+initSync({
+  module: readFileSync(new URL("../pkg/add_space_bg.wasm", import.meta.url)),
+});
+
+test("addSpace", () => {
+  expect(addSpace("中文English中文")).toBe("中文 English 中文");
+  expect(normalize("中 文 内 容 ， 和English")).toBe("中文内容，和 English");
+});
+
+test("options", () => {
+  const opts = new Options({ fullwidth_punctuation: true });
+  expect(opts.format("你好,世界")).toBe("你好，世界");
+  expect(opts.format("# 标题Title\n\n`中文code`", "markdown")).toBe(
+    "# 标题 Title\n\n`中文code`",
+  );
+  expect(opts.format("// 注释comment\nlet s = \"中文a\";", "rust")).toBe(
+    "// 注释 comment\nlet s = \"中文a\";",
+  );
+  expect(new Options().format("中文ab")).toBe("中文 ab");
+  expect(() => new Options({ scripts: ["Nope"] })).toThrow("unknown script: Nope");
+  expect(() => new Options({ spaces: true })).toThrow();
+  expect(() => new Options().format("a", "cobol")).toThrow();
+});
+
+test("edits", () => {
+  // offsets count UTF-16 code units, 😀 is two
+  const txt = "😀中文ab";
+  const edits = new Options().edits(txt);
+  expect(edits).toEqual([
+    {
+      start: 4,
+      end: 4,
+      text: " ",
+      rule: "CjkWord",
+      message: "missing space between CJK and Latin text",
+    },
+  ]);
+  expect(txt.slice(0, 4) + " " + txt.slice(4)).toBe("😀中文 ab");
+});
@@ -0,0 +1,11 @@
+#!/usr/bin/env bash
+
+DIR=$(realpath $0) && DIR=${DIR%/*}
+cd $DIR
+set -ex
+
+# the add_space_ffi wrapper owns the cdylib, the package keeps the add_space name
+wasm-pack build ../add_space_ffi --release --target web --scope 3- --out-dir $DIR/pkg \
+  --out-name add_space -- --features wasm
+sed -i.bak 's|"@3-/add_space_ffi"|"@3-/add_space"|' pkg/package.json && rm pkg/package.json.bak
+bun test tests/wasm.test.js
@@ -0,0 +1,7 @@
+//! Shared library of [`add_space`]: the `ffi` feature exports its C ABI, `wasm` its JavaScript
+//! API. It owns the `cdylib` so the library and the CLI build none.
+
+#[cfg(feature = "ffi")]
+pub use add_space::ffi::*;
+#[cfg(not(feature = "ffi"))]
+pub use add_space;